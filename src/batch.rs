@@ -0,0 +1,268 @@
+//! The `batch` subcommand: turns an image into a distance field or Voronoi
+//! diagram on disk without opening a window.
+
+use crate::engine::{Engine, Metric};
+use crate::export::ExportFormat;
+use crate::image_seeds::{SeedImage, SeedPredicate};
+use crate::sdf::{SdfFormat, SignedDistanceField};
+use anyhow::{bail, Context};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+usage: radiance_cascades batch <input> <output> [options]
+
+Reads a PNG or JPEG image whose non-white pixels are the shape or the seeds
+and writes the result in the size of the image.
+
+options:
+  --output <kind>     sdf (default), voronoi, labels or distance
+  --format <format>   png8 (default), png16, exr or raw, labels can't be
+                      png16
+  --spread <pixels>   distance at which PNG outputs saturate, EXR and raw
+                      outputs always keep the distances in pixels
+  --metric <metric>   euclidean (default), manhattan, chebyshev or minkowski:<p>
+  --alpha <threshold> seeds are pixels with an alpha above the threshold
+  --wrap              the image tiles, distances wrap around its edges
+  --half              computes the SDF in 16-bit floats, the default where
+                      32-bit floats can't be rendered to like on GLES
+";
+
+/// What the batch tool writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchOutput {
+    /// The signed distance to the edge of the seed pixels.
+    #[default]
+    Sdf,
+    /// The colour of every pixel's nearest seed pixel in the input.
+    Voronoi,
+    /// The packed colour of every pixel's nearest seed pixel, see
+    /// [`Engine::export_labels`].
+    Labels,
+    /// The unsigned distance to the nearest seed pixel.
+    Distance,
+}
+
+/// The arguments of the `batch` subcommand.
+#[derive(Debug, Clone)]
+pub struct Batch {
+    pub input: PathBuf,
+    pub output_path: PathBuf,
+    pub output: BatchOutput,
+    pub format: ExportFormat,
+    pub spread: Option<f32>,
+    pub metric: Metric,
+    pub predicate: SeedPredicate,
+    pub wrap: bool,
+    pub sdf_format: SdfFormat,
+}
+
+impl Batch {
+    /// Parses the arguments following `batch`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Batch> {
+        let mut args = args.into_iter();
+        let mut paths = Vec::new();
+        let mut batch = Batch {
+            input: PathBuf::new(),
+            output_path: PathBuf::new(),
+            output: BatchOutput::default(),
+            format: ExportFormat::default(),
+            spread: None,
+            metric: Metric::default(),
+            predicate: SeedPredicate::NonWhite,
+            wrap: false,
+            sdf_format: SdfFormat::default(),
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().with_context(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--output" => {
+                    batch.output = match value()?.as_str() {
+                        "sdf" => BatchOutput::Sdf,
+                        "voronoi" => BatchOutput::Voronoi,
+                        "labels" => BatchOutput::Labels,
+                        "distance" => BatchOutput::Distance,
+                        other => bail!("unknown output {other}"),
+                    }
+                }
+                "--format" => {
+                    batch.format = match value()?.as_str() {
+                        "png8" => ExportFormat::Png8,
+                        "png16" => ExportFormat::Png16,
+                        "exr" => ExportFormat::Exr,
+                        "raw" => ExportFormat::Raw,
+                        other => bail!("unknown format {other}"),
+                    }
+                }
+                "--spread" => {
+                    let spread = value()?;
+                    batch.spread = Some(
+                        spread
+                            .parse()
+                            .with_context(|| format!("invalid spread {spread}"))?,
+                    );
+                }
+                "--metric" => batch.metric = parse_metric(&value()?)?,
+                "--alpha" => {
+                    let threshold = value()?;
+                    batch.predicate = SeedPredicate::AlphaAbove(
+                        threshold
+                            .parse()
+                            .with_context(|| format!("invalid alpha threshold {threshold}"))?,
+                    );
+                }
+                "--wrap" => batch.wrap = true,
+                "--half" => batch.sdf_format = SdfFormat::R16Float,
+                option if option.starts_with("--") => bail!("unknown option {option}"),
+                _ => paths.push(PathBuf::from(arg)),
+            }
+        }
+
+        let [input, output_path] = <[PathBuf; 2]>::try_from(paths)
+            .ok()
+            .context("expected an input and an output path")?;
+        batch.input = input;
+        batch.output_path = output_path;
+
+        // The ids of seed pixels are their packed colours, see `SeedImage`
+        if batch.output == BatchOutput::Labels && batch.format == ExportFormat::Png16 {
+            bail!("labels are packed RGBA colours, which don't fit in png16, use png8, exr or raw");
+        }
+        Ok(batch)
+    }
+
+    /// Runs the engine on a headless device and writes the output.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let image = image::open(&self.input)
+            .with_context(|| format!("failed to load {}", self.input.display()))?
+            .to_rgba8();
+
+        // Machines without a GPU, like most servers, only have the software
+        // adapter
+        let mut engine = match Engine::headless(image.width(), image.height(), false).await {
+            Ok(engine) => engine,
+            Err(_) => Engine::headless(image.width(), image.height(), true).await?,
+        };
+        let config = engine.config_mut();
+        // The spread normalises the distance texture, which only PNGs need
+        if matches!(self.format, ExportFormat::Png8 | ExportFormat::Png16) {
+            config.spread = self.spread;
+        }
+        config.metric = self.metric;
+        config.wrap = self.wrap;
+
+        match self.output {
+            BatchOutput::Sdf => {
+                let mask: Vec<bool> = image
+                    .pixels()
+                    .map(|pixel| self.predicate.matches(pixel.0))
+                    .collect();
+                let mut sdf = SignedDistanceField::new(&engine, self.sdf_format)?;
+                sdf.generate(&mut engine, &mask)?;
+                sdf.export(&engine, &self.output_path, self.format).await
+            }
+            BatchOutput::Voronoi | BatchOutput::Labels | BatchOutput::Distance => {
+                let seeds = SeedImage::new(&image, self.predicate);
+                if seeds.seed_count() == 0 {
+                    bail!("{} has no seed pixels", self.input.display());
+                }
+                engine.set_seed_image(Some(seeds));
+                // The nearest seed pixel's colour is looked up in the material
+                engine.set_material_image(Some(image));
+                engine.run()?;
+
+                match self.output {
+                    BatchOutput::Voronoi => {
+                        engine.export_colors(&self.output_path, self.format).await
+                    }
+                    BatchOutput::Labels => {
+                        engine.export_labels(&self.output_path, self.format).await
+                    }
+                    _ => {
+                        engine
+                            .export_distances(&self.output_path, self.format)
+                            .await
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn parse_metric(metric: &str) -> anyhow::Result<Metric> {
+    let metric = match metric {
+        "euclidean" => Metric::Euclidean,
+        "manhattan" => Metric::Manhattan,
+        "chebyshev" => Metric::Chebyshev,
+        _ => match metric.strip_prefix("minkowski:") {
+            Some(p) => Metric::Minkowski(p.parse().with_context(|| format!("invalid p {p}"))?),
+            None => bail!("unknown metric {metric}"),
+        },
+    };
+    metric.validate()?;
+    Ok(metric)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn parse(args: &str) -> anyhow::Result<Batch> {
+        Batch::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn defaults() {
+        let batch = parse("in.png out.png").unwrap();
+
+        assert_eq!(batch.input, Path::new("in.png"));
+        assert_eq!(batch.output_path, Path::new("out.png"));
+        assert_eq!(batch.output, BatchOutput::Sdf);
+        assert_eq!(batch.format, ExportFormat::Png8);
+        assert_eq!(batch.spread, None);
+        assert_eq!(batch.metric, Metric::Euclidean);
+        assert_eq!(batch.predicate, SeedPredicate::NonWhite);
+        assert!(!batch.wrap);
+        assert_eq!(batch.sdf_format, SdfFormat::R32Float);
+    }
+
+    #[test]
+    fn options() {
+        let batch = parse(
+            "--output distance in.png --format exr --spread 4.5 --metric minkowski:3 \
+             --alpha 10 --wrap --half out.exr",
+        )
+        .unwrap();
+
+        assert_eq!(batch.input, Path::new("in.png"));
+        assert_eq!(batch.output_path, Path::new("out.exr"));
+        assert_eq!(batch.output, BatchOutput::Distance);
+        assert_eq!(batch.format, ExportFormat::Exr);
+        assert_eq!(batch.spread, Some(4.5));
+        assert_eq!(batch.metric, Metric::Minkowski(3.));
+        assert_eq!(batch.predicate, SeedPredicate::AlphaAbove(10));
+        assert!(batch.wrap);
+        assert_eq!(batch.sdf_format, SdfFormat::R16Float);
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        for args in [
+            "in.png",
+            "in.png out.png extra.png",
+            "in.png out.png --output",
+            "in.png out.png --output mesh",
+            "in.png out.png --format jpeg",
+            "in.png out.png --spread far",
+            "in.png out.png --metric hamming",
+            "in.png out.png --metric minkowski:0",
+            "in.png out.png --metric minkowski:-1",
+            "in.png out.png --alpha 256",
+            "in.png out.png --output labels --format png16",
+            "in.png out.png --verbose",
+        ] {
+            assert!(parse(args).is_err(), "{args}");
+        }
+    }
+}
@@ -0,0 +1,83 @@
+@group(0) @binding(0)
+var t_distance: texture_2d<f32>;
+@group(0) @binding(1)
+var t_material: texture_2d<f32>;
+
+// The merged cascade above, unused by the top cascade
+@group(1) @binding(0)
+var t_probes: texture_2d<f32>;
+@group(1) @binding(1)
+var t_cascade: texture_storage_2d<rgba16float, write>;
+
+@group(2) @binding(0)
+var<uniform> dimensions: vec2<f32>;
+
+const TAU = 6.283185307179586;
+
+// Traces the rays of one cascade and merges them with the cascade above.
+// Every texel is the radiance reaching its probe from one direction, from
+// the cascade's interval and everything beyond, in rgb.
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let block = 2u << cascade.index;
+    let probe = id.xy / block;
+    if any(probe >= probe_count(cascade.spacing)) {
+        return;
+    }
+
+    let direction_index = (id.y % block) * block + id.x % block;
+    let angle = TAU * (f32(direction_index) + 0.5) / f32(block * block);
+    let direction = vec2<f32>(cos(angle), sin(angle));
+    let origin = probe_position(probe, cascade.spacing);
+    let start = origin + direction * cascade.interval_start;
+
+    if cascade.is_top == 1u {
+        let radiance = trace(start, origin + direction * cascade.interval_end);
+        textureStore(t_cascade, id.xy, vec4<f32>(radiance.rgb, 0.));
+        return;
+    }
+
+    // Each ray ends where the rays of one of the nearest probes above start,
+    // so the intervals join up without gaps or overlaps, and continues with
+    // the four directions of that probe that split this one
+    let spacing = 2. * cascade.spacing;
+    var radiance = vec4<f32>(0.);
+    for (var i = 0u; i < 4u; i++) {
+        let corner = probe_corner(origin, spacing, i);
+        let end = probe_position(corner.probe, spacing) + direction * cascade.interval_end;
+        var merged = trace(start, end);
+        if merged.a > 0. {
+            merged += merged.a * probe_directions(corner.probe, 2u * block, 4u * direction_index);
+        }
+        radiance += corner.weight * vec4<f32>(merged.rgb, 0.);
+    }
+
+    textureStore(t_cascade, id.xy, radiance);
+}
+
+// The radiance along the segment from `start` to `end` in rgb, 1 in alpha
+// if nothing blocks it. Only the part inside the texture is traced.
+fn trace(start: vec2<f32>, end: vec2<f32>) -> vec4<f32> {
+    let delta = end - start;
+    let length = length(delta);
+    if length == 0. {
+        return vec4<f32>(0., 0., 0., 1.);
+    }
+    let direction = delta / length;
+
+    // Clip to the texture, nothing outside blocks or emits light
+    let inverse = 1. / select(direction, vec2<f32>(1e-9), direction == vec2<f32>(0.));
+    let t0 = (vec2<f32>(0.) - start) * inverse;
+    let t1 = (dimensions - start) * inverse;
+    let enter = max(max(min(t0.x, t1.x), min(t0.y, t1.y)), 0.);
+    let exit = min(min(max(t0.x, t1.x), max(t0.y, t1.y)), length);
+    if enter >= exit {
+        return vec4<f32>(0., 0., 0., 1.);
+    }
+
+    let hit = ray_march(start + direction * enter, direction, exit - enter);
+    if hit.hit {
+        return vec4<f32>(hit.material.rgb, 0.);
+    }
+    return vec4<f32>(0., 0., 0., 1.);
+}
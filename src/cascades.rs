@@ -0,0 +1,538 @@
+//! 2D global illumination with radiance cascades, ray marched through a jump
+//! flood distance field.
+
+use crate::engine::{
+    self, create_compute_bind_group, create_compute_pipeline, create_texture_bind_group, Engine,
+    WORKGROUP_SIZE,
+};
+use crate::raymarch::{create_scene_bind_group, create_scene_bind_group_layout, SurfaceField};
+use wgpu::{self, BindGroup, BindGroupLayout, Buffer, Device};
+
+/// Format of the probe and fluence textures.
+pub const CASCADE_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Settings of [`RadianceCascades`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CascadeConfig {
+    /// Distance between the probes of the first cascade in pixels, doubling
+    /// with every cascade. The probe textures have about the size of the
+    /// engine divided by half the spacing.
+    pub probe_spacing: u32,
+    /// Length of the rays of the first cascade in pixels. The rays of every
+    /// further cascade start where the previous ones end and are four
+    /// times as long.
+    pub interval: f32,
+    /// Number of cascades. Defaults to as many as needed for the rays of the
+    /// top cascade to reach across the diagonal.
+    pub cascade_count: Option<u32>,
+}
+
+impl Default for CascadeConfig {
+    fn default() -> Self {
+        Self {
+            probe_spacing: 2,
+            interval: 2.,
+            cascade_count: None,
+        }
+    }
+}
+
+impl CascadeConfig {
+    /// The number of cascades for a texture size.
+    pub fn cascade_count(&self, width: u32, height: u32) -> u32 {
+        self.cascade_count.unwrap_or_else(|| {
+            let diagonal = (width as f32).hypot(height as f32);
+            let mut count = 1;
+            while interval_start(self.interval, count) < diagonal {
+                count += 1;
+            }
+            count
+        })
+    }
+}
+
+/// The constants of one cascade, matches `Cascade` in probes.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CascadeUniform {
+    index: u32,
+    is_top: u32,
+    spacing: f32,
+    interval_start: f32,
+    interval_end: f32,
+    _padding: [u32; 3],
+}
+
+/// Lights the scene of an [`Engine`]'s material texture: every pixel that
+/// is neither white nor transparent is a surface emitting its colour, black
+/// ones only block light.
+///
+/// Rays are sphere traced through a [`SurfaceField`].
+/// Probes with few short rays close together capture nearby light, probes
+/// with many long rays far apart the light from further away. Each cascade
+/// is merged with the one above it, from the top down, every ray ending
+/// where the rays of the nearest probes above start, and the first one
+/// gives the fluence of every pixel.
+///
+/// Reuses the ping-pong and distance textures of the engine, so its
+/// nearest-seed and distance outputs are overwritten by
+/// [`RadianceCascades::render`].
+pub struct RadianceCascades {
+    config: CascadeConfig,
+    cascade_buffer: Buffer,
+    cascade_bind_group: BindGroup,
+    scene_bind_group_layout: BindGroupLayout,
+    probes_bind_group_layout: BindGroupLayout,
+    fluence_bind_group_layout: BindGroupLayout,
+    textures: CascadeTextures,
+    surface: SurfaceField,
+    cascade_compute_pipeline: wgpu::ComputePipeline,
+    fluence_compute_pipeline: wgpu::ComputePipeline,
+}
+
+impl RadianceCascades {
+    pub fn new(engine: &Engine, config: CascadeConfig) -> RadianceCascades {
+        let device = engine.device();
+
+        let cascade_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cascade Buffer"),
+            size: std::mem::size_of::<CascadeUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let cascade_bind_group_layout = engine::create_uniform_bind_group_layout(
+            device,
+            "cascade_bind_group_layout",
+            wgpu::ShaderStages::COMPUTE,
+        );
+
+        let cascade_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &cascade_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: cascade_buffer.as_entire_binding(),
+            }],
+            label: Some("cascade_bind_group"),
+        });
+
+        let texture_entry = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type,
+            },
+            count: None,
+        };
+
+        let scene_bind_group_layout = create_scene_bind_group_layout(device);
+
+        // The cascade above and the one being written
+        let probes_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    texture_entry(0, wgpu::TextureSampleType::Float { filterable: false }),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: CASCADE_TEXTURE_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("probes_bind_group_layout"),
+            });
+
+        let fluence_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                    ..texture_entry(0, wgpu::TextureSampleType::Float { filterable: false })
+                }],
+                label: Some("fluence_bind_group_layout"),
+            });
+
+        let textures = CascadeTextures::new(
+            device,
+            &probes_bind_group_layout,
+            &fluence_bind_group_layout,
+            &config,
+            engine.width(),
+            engine.height(),
+        );
+
+        // The fluence pass shares the layout, reading the first cascade
+        // instead of the one above
+        let cascade_compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Cascade Compute Pipeline Layout"),
+                bind_group_layouts: &[
+                    &scene_bind_group_layout,
+                    &probes_bind_group_layout,
+                    engine.dimensions_bind_group_layout(),
+                    &cascade_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let cascade_compute_pipeline = create_compute_pipeline(
+            "Cascade Compute Pipeline",
+            device,
+            &cascade_compute_pipeline_layout,
+            shader!("cascade.wgsl", "probes.wgsl", "raymarch.wgsl"),
+        );
+
+        let fluence_compute_pipeline = create_compute_pipeline(
+            "Fluence Compute Pipeline",
+            device,
+            &cascade_compute_pipeline_layout,
+            shader!("fluence.wgsl", "probes.wgsl"),
+        );
+
+        Self {
+            config,
+            cascade_buffer,
+            cascade_bind_group,
+            scene_bind_group_layout,
+            probes_bind_group_layout,
+            fluence_bind_group_layout,
+            textures,
+            surface: SurfaceField::new(engine),
+            cascade_compute_pipeline,
+            fluence_compute_pipeline,
+        }
+    }
+
+    pub fn config(&self) -> &CascadeConfig {
+        &self.config
+    }
+
+    /// Changes to the probe spacing or the intervals take effect on the
+    /// next [`RadianceCascades::resize`] or [`RadianceCascades::render`].
+    pub fn config_mut(&mut self) -> &mut CascadeConfig {
+        &mut self.config
+    }
+
+    /// The fluence of every pixel after the last
+    /// [`RadianceCascades::render`], normalised to the mean radiance over
+    /// all directions, in [`CASCADE_TEXTURE_FORMAT`].
+    pub fn fluence_texture(&self) -> &wgpu::Texture {
+        &self.textures.fluence_texture
+    }
+
+    /// Layout of [`RadianceCascades::fluence_bind_group`], a non-filterable
+    /// `texture_2d<f32>`.
+    pub fn fluence_bind_group_layout(&self) -> &BindGroupLayout {
+        &self.fluence_bind_group_layout
+    }
+
+    /// Recreated by [`RadianceCascades::resize`].
+    pub fn fluence_bind_group(&self) -> &BindGroup {
+        &self.textures.fluence_bind_group
+    }
+
+    /// Reallocates the textures for the size of the engine and the current
+    /// config, if they changed.
+    pub fn resize(&mut self, engine: &Engine) {
+        let size = probe_texture_size(&self.config, engine.width(), engine.height());
+        if (engine.width(), engine.height(), size)
+            == (
+                self.textures.width,
+                self.textures.height,
+                self.textures.probe_size,
+            )
+        {
+            return;
+        }
+
+        self.textures = CascadeTextures::new(
+            engine.device(),
+            &self.probes_bind_group_layout,
+            &self.fluence_bind_group_layout,
+            &self.config,
+            engine.width(),
+            engine.height(),
+        );
+    }
+
+    /// Computes the fluence of the material texture of the last
+    /// [`Engine::run`].
+    pub fn render(&mut self, engine: &mut Engine) {
+        self.resize(engine);
+        let (width, height) = (engine.width(), engine.height());
+
+        self.surface.generate(engine);
+        let scene_bind_group = create_scene_bind_group(
+            engine.device(),
+            &self.scene_bind_group_layout,
+            engine.distance_texture(),
+            engine.material_texture(),
+        );
+
+        // Top down, the first cascade is written to texture a
+        let count = self.config.cascade_count(width, height);
+        let probe_size = self.textures.probe_size;
+        for index in (0..count).rev() {
+            let spacing = (self.config.probe_spacing.max(1) << index) as f32;
+            let uniform = CascadeUniform {
+                index,
+                is_top: (index == count - 1) as u32,
+                spacing,
+                interval_start: interval_start(self.config.interval, index),
+                interval_end: interval_start(self.config.interval, index + 1),
+                _padding: [0; 3],
+            };
+            engine
+                .queue()
+                .write_buffer(&self.cascade_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+            let probes_bind_group = match index % 2 {
+                0 => &self.textures.probes_from_b_bind_group,
+                _ => &self.textures.probes_from_a_bind_group,
+            };
+            self.dispatch(
+                engine,
+                &self.cascade_compute_pipeline,
+                &scene_bind_group,
+                probes_bind_group,
+                probe_size,
+            );
+        }
+
+        self.dispatch(
+            engine,
+            &self.fluence_compute_pipeline,
+            &scene_bind_group,
+            &self.textures.fluence_from_a_bind_group,
+            [width, height],
+        );
+    }
+
+    fn dispatch(
+        &self,
+        engine: &Engine,
+        pipeline: &wgpu::ComputePipeline,
+        scene_bind_group: &BindGroup,
+        probes_bind_group: &BindGroup,
+        [width, height]: [u32; 2],
+    ) {
+        let mut encoder = engine.new_encoder();
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Cascade Compute Pass"),
+                timestamp_writes: None,
+            });
+
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, scene_bind_group, &[]);
+            compute_pass.set_bind_group(1, probes_bind_group, &[]);
+            compute_pass.set_bind_group(2, engine.dimensions_bind_group(), &[]);
+            compute_pass.set_bind_group(3, &self.cascade_bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                width.div_ceil(WORKGROUP_SIZE),
+                height.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        engine.queue().submit(std::iter::once(encoder.finish()));
+    }
+}
+
+/// The distance from a probe at which the rays of a cascade start, the sum
+/// of the intervals of the cascades below.
+fn interval_start(interval: f32, index: u32) -> f32 {
+    interval * (4f32.powi(index as i32) - 1.) / 3.
+}
+
+/// The size of the texture that fits the probes of every cascade, see
+/// `probe_count` in probes.wgsl. Each probe is a block of texels, one per
+/// direction, with twice the side of the block below.
+fn probe_texture_size(config: &CascadeConfig, width: u32, height: u32) -> [u32; 2] {
+    (0..config.cascade_count(width, height))
+        .map(|index| {
+            let spacing = config.probe_spacing.max(1) << index;
+            let block = 2 << index;
+            [
+                ((width - 1).div_ceil(spacing) + 1) * block,
+                ((height - 1).div_ceil(spacing) + 1) * block,
+            ]
+        })
+        .fold([1, 1], |[w, h], [x, y]| [w.max(x), h.max(y)])
+}
+
+/// The ping-pong probe textures and the fluence texture, recreated when the
+/// engine size or the config changes.
+struct CascadeTextures {
+    width: u32,
+    height: u32,
+    probe_size: [u32; 2],
+    fluence_texture: wgpu::Texture,
+    probes_from_a_bind_group: BindGroup,
+    probes_from_b_bind_group: BindGroup,
+    fluence_from_a_bind_group: BindGroup,
+    fluence_bind_group: BindGroup,
+}
+
+impl CascadeTextures {
+    fn new(
+        device: &Device,
+        probes_layout: &BindGroupLayout,
+        fluence_layout: &BindGroupLayout,
+        config: &CascadeConfig,
+        width: u32,
+        height: u32,
+    ) -> CascadeTextures {
+        let probe_size = probe_texture_size(config, width, height);
+        let texture_desc = wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: probe_size[0],
+                height: probe_size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: CASCADE_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+            view_formats: &[],
+            label: Some("Probe Texture"),
+        };
+
+        let probe_texture_a = device.create_texture(&texture_desc);
+        let probe_texture_b = device.create_texture(&texture_desc);
+        let fluence_texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            label: Some("Fluence Texture"),
+            ..texture_desc
+        });
+
+        let probe_texture_a_view = probe_texture_a.create_view(&Default::default());
+        let probe_texture_b_view = probe_texture_b.create_view(&Default::default());
+        let fluence_texture_view = fluence_texture.create_view(&Default::default());
+
+        let probes_from_a_bind_group = create_compute_bind_group(
+            device,
+            probes_layout,
+            &probe_texture_a_view,
+            &probe_texture_b_view,
+            "probes_from_a_bind_group",
+        );
+        let probes_from_b_bind_group = create_compute_bind_group(
+            device,
+            probes_layout,
+            &probe_texture_b_view,
+            &probe_texture_a_view,
+            "probes_from_b_bind_group",
+        );
+        let fluence_from_a_bind_group = create_compute_bind_group(
+            device,
+            probes_layout,
+            &probe_texture_a_view,
+            &fluence_texture_view,
+            "fluence_from_a_bind_group",
+        );
+        let fluence_bind_group = create_texture_bind_group(
+            device,
+            fluence_layout,
+            &fluence_texture_view,
+            "fluence_bind_group",
+        );
+
+        Self {
+            width,
+            height,
+            probe_size,
+            fluence_texture,
+            probes_from_a_bind_group,
+            probes_from_b_bind_group,
+            fluence_from_a_bind_group,
+            fluence_bind_group,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path_tracer::PathTracer;
+    use crate::seeds::Seed;
+
+    #[test]
+    fn lights_the_surroundings_of_a_seed() {
+        let (width, height) = (40, 30);
+        let mut engine = pollster::block_on(Engine::headless(width, height, true)).unwrap();
+        engine
+            .add_seed(Seed::new(1, [20., 15.], 3., [1., 0., 0., 1.]))
+            .unwrap();
+        engine.run().unwrap();
+
+        let mut cascades = RadianceCascades::new(&engine, CascadeConfig::default());
+        cascades.render(&mut engine);
+        let fluence = pollster::block_on(cascades.read(&engine)).unwrap();
+        let at = |x: u32, y: u32| fluence[(y * width + x) as usize];
+
+        // Inside the emitter every ray hits it
+        assert_eq!(at(22, 15)[0], 1.);
+        // Less of the view is covered by the emitter further away
+        assert!(at(26, 15)[0] > at(34, 15)[0]);
+        assert!(at(34, 15)[0] > at(0, 0)[0]);
+        assert!(at(0, 0)[0] > 0.);
+        assert!(fluence.iter().all(|rgba| rgba[1] == 0. && rgba[2] == 0.));
+    }
+
+    #[test]
+    fn matches_the_path_tracer() {
+        let (width, height) = (40, 30);
+        let mut engine = pollster::block_on(Engine::headless(width, height, true)).unwrap();
+        engine
+            .add_seed(Seed::new(1, [20., 15.], 3., [1., 0., 0., 1.]))
+            .unwrap();
+        engine.run().unwrap();
+
+        let mut cascades = RadianceCascades::new(&engine, CascadeConfig::default());
+        cascades.render(&mut engine);
+        let fluence = pollster::block_on(cascades.read(&engine)).unwrap();
+
+        let mut path_tracer = PathTracer::new(&engine, 64);
+        for _ in 0..32 {
+            path_tracer.render(&mut engine);
+        }
+        let reference = pollster::block_on(path_tracer.read(&engine)).unwrap();
+
+        let at = |x: u32, y: u32| {
+            let i = (y * width + x) as usize;
+            (fluence[i][0], reference[i][0])
+        };
+
+        // Inside the emitter every ray hits it
+        assert_eq!(at(22, 15).0, 1.);
+
+        for (x, y) in [(30, 15), (0, 0)] {
+            let (fluence, reference) = at(x, y);
+            assert!(
+                (fluence - reference).abs() < 0.1 * reference + 0.005,
+                "({x}, {y}): {fluence} instead of {reference}"
+            );
+        }
+
+        let mean_error = fluence
+            .iter()
+            .zip(&reference)
+            .map(|(fluence, reference)| (fluence[0] - reference[0]).abs())
+            .sum::<f32>()
+            / fluence.len() as f32;
+        assert!(mean_error < 0.016, "mean error {mean_error}");
+    }
+}
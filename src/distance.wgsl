@@ -0,0 +1,30 @@
+@group(0) @binding(0)
+var t_jfa: texture_2d<u32>;
+@group(0) @binding(1)
+var t_distance: texture_storage_2d<r32float, write>;
+
+// Distance at which the output saturates at 1, or 0 to keep pixels
+@group(1) @binding(0)
+var<uniform> spread: f32;
+
+@group(2) @binding(0)
+var<uniform> dimensions: vec2<f32>;
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= vec2<u32>(dimensions)) {
+        return;
+    }
+
+    // Without any seed, the nearest one is at least the diagonal away
+    var nearest = length(dimensions);
+    let closest = textureLoad(t_jfa, id.xy, 0);
+    if !all(closest.xy == NO_SEED) {
+        nearest = pixel_distance(vec2<f32>(id.xy), vec2<f32>(closest.xy));
+    }
+    if spread > 0. {
+        nearest = min(nearest / spread, 1.);
+    }
+
+    textureStore(t_distance, id.xy, vec4<f32>(nearest, 0., 0., 1.));
+}
@@ -0,0 +1,1631 @@
+use crate::image_seeds::SeedImage;
+use crate::primitives::Primitive;
+use crate::seeds::{Seed, SeedBuffer};
+use anyhow::Context;
+use wgpu::{
+    self, util::DeviceExt, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, Queue,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Vertex {
+    position: [f32; 2],
+}
+
+impl Vertex {
+    pub(crate) fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 0,
+                format: wgpu::VertexFormat::Float32x2,
+            }],
+        }
+    }
+}
+
+const VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [-1., 1.],
+    },
+    Vertex {
+        position: [-1., -3.],
+    },
+    Vertex { position: [3., 1.] },
+];
+
+/// Format of the ping-pong textures: the exact integer pixel coordinates of
+/// the nearest seed in the first two channels, or [`NO_SEED`] in both, the
+/// seed's id in the third and the bits of its `f32` weight in the fourth.
+///
+/// Unlike `Rg32Uint`, four channels can be used as a storage texture on GLES.
+pub const JFA_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Uint;
+
+/// Format of the material texture. Colours are stored sRGB encoded, so
+/// images can be uploaded as they are and shaders read linear colours.
+pub const MATERIAL_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Format of the distance texture written after the jump flood.
+pub const DISTANCE_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+
+/// Marks a texel that has no nearest seed (yet).
+pub const NO_SEED: u32 = u32::MAX;
+
+/// Must match `@workgroup_size` in the compute shaders.
+pub(crate) const WORKGROUP_SIZE: u32 = 8;
+
+pub(crate) const NO_SEED_COLOR: wgpu::Color = wgpu::Color {
+    r: NO_SEED as f64,
+    g: NO_SEED as f64,
+    b: 0.,
+    a: 0.,
+};
+
+/// How the jump flood steps are executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum JfaBackend {
+    /// A full-screen triangle per step, rendering into the other ping-pong texture.
+    #[default]
+    Fragment,
+    /// A dispatch per step, writing the other ping-pong texture as a storage
+    /// texture. Useful where render attachments are unavailable.
+    Compute,
+}
+
+/// Extra passes around the main step schedule, trading performance for
+/// fewer misclassified pixels.
+#[derive(Debug, Clone, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum JfaVariant {
+    /// Only the main schedule.
+    #[default]
+    Jfa,
+    /// 1+JFA: a step 1 pass before the main schedule.
+    OnePlusJfa,
+    /// JFA+1: an additional step 1 pass at the end.
+    JfaPlusOne,
+    /// JFA+2: additional step 2 and step 1 passes at the end.
+    JfaPlusTwo,
+    /// Arbitrary steps run before and after the main schedule.
+    Custom { pre: Vec<u32>, post: Vec<u32> },
+}
+
+impl JfaVariant {
+    /// The steps run before and after the main schedule.
+    pub fn extra_steps(&self) -> (&[u32], &[u32]) {
+        match self {
+            JfaVariant::Jfa => (&[], &[]),
+            JfaVariant::OnePlusJfa => (&[1], &[]),
+            JfaVariant::JfaPlusOne => (&[], &[1]),
+            JfaVariant::JfaPlusTwo => (&[], &[2, 1]),
+            JfaVariant::Custom { pre, post } => (pre, post),
+        }
+    }
+}
+
+/// How the distance between a pixel and a seed is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub enum Metric {
+    /// L2, round Voronoi cells.
+    #[default]
+    Euclidean,
+    /// L1, the number of 4-connected steps.
+    Manhattan,
+    /// L∞, the number of 8-connected steps.
+    Chebyshev,
+    /// Lp for any `p >= 1`, between Manhattan (1) and Chebyshev (infinity).
+    Minkowski(f32),
+}
+
+impl Metric {
+    /// The length of the difference between two pixels.
+    pub fn length(self, [dx, dy]: [f32; 2]) -> f32 {
+        let (dx, dy) = (dx.abs(), dy.abs());
+        match self {
+            Metric::Euclidean => dx.hypot(dy),
+            Metric::Manhattan => dx + dy,
+            Metric::Chebyshev => dx.max(dy),
+            Metric::Minkowski(p) => (dx.powf(p) + dy.powf(p)).powf(p.recip()),
+        }
+    }
+
+    /// Fails for a Minkowski exponent that isn't a finite number of at
+    /// least 1, which isn't a distance.
+    pub fn validate(self) -> anyhow::Result<()> {
+        match self {
+            Metric::Minkowski(p) if !(p.is_finite() && p >= 1.) => {
+                anyhow::bail!("the Minkowski exponent must be at least 1, not {p}")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How [`crate::seeds::Seed::weight`] changes the distance `d` to a seed
+/// when looking for the nearest one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum Weighting {
+    /// Weights are ignored.
+    #[default]
+    None,
+    /// `d - weight`, cells with hyperbolic edges.
+    Additive,
+    /// `d / weight`, cells with circular edges, like crystals growing at
+    /// different speeds.
+    Multiplicative,
+    /// `d² - weight`, a power diagram with straight edges where the weight
+    /// is the squared radius of the seed's circle.
+    Power,
+}
+
+impl Weighting {
+    /// The distance to a seed as the jump flood compares it.
+    pub fn apply(self, distance: f32, weight: f32) -> f32 {
+        match self {
+            Weighting::None => distance,
+            Weighting::Additive => distance - weight,
+            Weighting::Multiplicative => distance / weight,
+            Weighting::Power => distance * distance - weight,
+        }
+    }
+}
+
+/// Settings of the jump flood passes.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    pub backend: JfaBackend,
+    pub variant: JfaVariant,
+    /// Length of the first step in pixels. Defaults to half of
+    /// `max(width, height)` rounded up to a power of two.
+    pub start_step: Option<u32>,
+    /// Number of steps, each halving the previous one down to 1. Defaults
+    /// to as many as needed to reach a step of 1; further steps stay at 1.
+    pub iterations: Option<u32>,
+    /// Distance in pixels at which the distance texture saturates at 1.
+    /// `None` keeps the distances in pixels.
+    pub spread: Option<f32>,
+    /// Whether the domain wraps around its edges like a torus, so the
+    /// results tile seamlessly.
+    pub wrap: bool,
+    /// Used for the jump flood comparisons and all distance outputs.
+    pub metric: Metric,
+    /// Only changes which seed is the nearest, the distance outputs stay
+    /// unweighted.
+    pub weighting: Weighting,
+}
+
+impl EngineConfig {
+    /// The step lengths of the jump flood passes for a texture size,
+    /// including the extra passes of the variant.
+    pub fn step_schedule(&self, width: u32, height: u32) -> Vec<u32> {
+        let start = self
+            .start_step
+            .unwrap_or(width.max(height).next_power_of_two() / 2)
+            .max(1);
+        let iterations = self.iterations.unwrap_or(start.ilog2() + 1);
+        let (pre, post) = self.variant.extra_steps();
+
+        let main = (0..iterations).map(|i| start.checked_shr(i).unwrap_or(0).max(1));
+        pre.iter()
+            .copied()
+            .chain(main)
+            .chain(post.iter().copied())
+            .map(|step| step.max(1))
+            .collect()
+    }
+
+    /// Fails for settings the passes can't run with.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.metric.validate()
+    }
+
+    /// The distance between two pixels as the shaders measure it, taking
+    /// [`EngineConfig::wrap`] into account for a texture size.
+    pub fn distance(&self, a: [f32; 2], b: [f32; 2], width: u32, height: u32) -> f32 {
+        let mut delta = [(a[0] - b[0]).abs(), (a[1] - b[1]).abs()];
+        if self.wrap {
+            delta[0] = delta[0].min(width as f32 - delta[0]);
+            delta[1] = delta[1].min(height as f32 - delta[1]);
+        }
+        self.metric.length(delta)
+    }
+}
+
+/// The settings of [`EngineConfig`] the shaders read, matches `Options` in
+/// options.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct JfaOptions {
+    wrap: u32,
+    metric: u32,
+    p: f32,
+    weighting: u32,
+}
+
+impl JfaOptions {
+    fn new(config: &EngineConfig) -> JfaOptions {
+        // Only Minkowski reads the exponent
+        let (metric, p) = match config.metric {
+            Metric::Euclidean => (0, 0.),
+            Metric::Manhattan => (1, 0.),
+            Metric::Chebyshev => (2, 0.),
+            Metric::Minkowski(p) => (3, p),
+        };
+
+        JfaOptions {
+            wrap: config.wrap as u32,
+            metric,
+            p,
+            weighting: config.weighting as u32,
+        }
+    }
+}
+
+/// The jump flood engine.
+///
+/// Owns only the `Adapter`/`Device`/`Queue`, the ping-pong textures and the
+/// seed/JFA pipelines, so it can run without a window (e.g. on a server or in tests).
+/// [`crate::jfa::State`] wraps it to present the result to a surface.
+pub struct Engine {
+    adapter: wgpu::Adapter,
+    device: Device,
+    queue: Queue,
+    seeds: SeedBuffer,
+    seeds_bind_group_layout: BindGroupLayout,
+    dimensions_buffer: Buffer,
+    dimensions_bind_group_layout: BindGroupLayout,
+    dimensions_bind_group: BindGroup,
+    step_bind_group: BindGroup,
+    step_buffer: Buffer,
+    spread_bind_group: BindGroup,
+    spread_buffer: Buffer,
+    options_bind_group_layout: BindGroupLayout,
+    options_bind_group: BindGroup,
+    options_buffer: Buffer,
+    material_sampler: wgpu::Sampler,
+    jfa_bind_group_layout: BindGroupLayout,
+    jfa_compute_bind_group_layout: BindGroupLayout,
+    material_bind_group_layout: BindGroupLayout,
+    distance_bind_group_layout: BindGroupLayout,
+    distance_compute_bind_group_layout: BindGroupLayout,
+    textures: Textures,
+    color_render_pipeline: wgpu::RenderPipeline,
+    initial_render_pipeline: wgpu::RenderPipeline,
+    jfa_render_pipeline: wgpu::RenderPipeline,
+    jfa_compute_pipeline: wgpu::ComputePipeline,
+    distance_compute_pipeline: wgpu::ComputePipeline,
+    config: EngineConfig,
+    // Replaces the seed pass, uploaded at the texture size
+    seed_image: Option<(SeedImage, wgpu::Texture)>,
+    // Replaces the seed discs in the material texture
+    material_image: Option<image::RgbaImage>,
+    vertex_buffer: Buffer,
+    clear_color: wgpu::Color,
+    // Whether the last JFA pass wrote to texture_b (otherwise texture_a)
+    output_is_b: bool,
+}
+
+impl Engine {
+    /// Creates an engine on an offscreen adapter, without any surface.
+    ///
+    /// With `force_fallback_adapter` the software adapter is requested, which
+    /// lets the engine run on machines without a GPU.
+    pub async fn headless(
+        width: u32,
+        height: u32,
+        force_fallback_adapter: bool,
+    ) -> anyhow::Result<Engine> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter,
+            })
+            .await
+            .context("no suitable adapter found")?;
+
+        let (device, queue) = adapter
+            .request_device(&device_descriptor(), None)
+            .await
+            .context("failed to request device")?;
+
+        Ok(Self::new(adapter, device, queue, width, height))
+    }
+
+    /// Creates an engine from an already requested device and the adapter
+    /// it was requested from.
+    pub fn new(
+        adapter: wgpu::Adapter,
+        device: Device,
+        queue: Queue,
+        width: u32,
+        height: u32,
+    ) -> Engine {
+        // ------
+        // Unfiforms/Buffers and Bind Groups for the initial render
+        // ------
+        let seeds_bind_group_layout = SeedBuffer::create_bind_group_layout(&device);
+        let seeds = SeedBuffer::new(&device, &seeds_bind_group_layout);
+
+        let dimensions_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Dimensions Buffer"),
+            contents: bytemuck::cast_slice(&[width as f32, height as f32]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let dimensions_bind_group_layout = create_uniform_bind_group_layout(
+            &device,
+            "Dimensions BGL",
+            wgpu::ShaderStages::VERTEX_FRAGMENT | wgpu::ShaderStages::COMPUTE,
+        );
+
+        let dimensions_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &dimensions_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: dimensions_buffer.as_entire_binding(),
+            }],
+            label: Some("dimensions_bind_group"),
+        });
+
+        // ------
+        // Ping Pong Textures
+        // ------
+        let material_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // The ping-pong textures hold integers, so they are read with
+        // textureLoad and don't need a sampler
+        let jfa_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Uint,
+                    },
+                    count: None,
+                }],
+                label: Some("jfa_bind_group_layout"),
+            });
+
+        // Reads one ping-pong texture and writes the other
+        let jfa_compute_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Uint,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: JFA_TEXTURE_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("jfa_compute_bind_group_layout"),
+            });
+
+        let material_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        // This should match the filterable field of the
+                        // corresponding Texture entry above.
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("material_bind_group_layout"),
+            });
+
+        // R32Float can't be filtered without an optional feature
+        let distance_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                }],
+                label: Some("distance_bind_group_layout"),
+            });
+
+        // Reads a ping-pong texture and writes the distance texture. R32Float
+        // can't be a render target on GLES, but can be a storage texture
+        let distance_compute_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Uint,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: DISTANCE_TEXTURE_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("distance_compute_bind_group_layout"),
+            });
+
+        let textures = Textures::new(
+            &device,
+            &TextureLayouts {
+                jfa: &jfa_bind_group_layout,
+                jfa_compute: &jfa_compute_bind_group_layout,
+                material: &material_bind_group_layout,
+                distance: &distance_bind_group_layout,
+                distance_compute: &distance_compute_bind_group_layout,
+            },
+            &material_sampler,
+            width,
+            height,
+        );
+
+        // ------
+        // Step Bind Group Layout
+        // ------
+        let step_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Step Buffer"),
+            contents: bytemuck::cast_slice(&[0u32]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let step_bind_group_layout = create_uniform_bind_group_layout(
+            &device,
+            "step_bind_group_layout",
+            wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+        );
+
+        let step_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &step_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: step_buffer.as_entire_binding(),
+            }],
+            label: Some("step_bind_group"),
+        });
+
+        // ------
+        // Spread Bind Group
+        // ------
+        let spread_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Spread Buffer"),
+            contents: bytemuck::cast_slice(&[0f32]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let spread_bind_group_layout = create_uniform_bind_group_layout(
+            &device,
+            "spread_bind_group_layout",
+            wgpu::ShaderStages::COMPUTE,
+        );
+
+        let spread_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &spread_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: spread_buffer.as_entire_binding(),
+            }],
+            label: Some("spread_bind_group"),
+        });
+
+        // ------
+        // Options Bind Group
+        // ------
+        let options_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Options Buffer"),
+            contents: bytemuck::cast_slice(&[JfaOptions::new(&EngineConfig::default())]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let options_bind_group_layout = create_uniform_bind_group_layout(
+            &device,
+            "options_bind_group_layout",
+            wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+        );
+
+        let options_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &options_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: options_buffer.as_entire_binding(),
+            }],
+            label: Some("options_bind_group"),
+        });
+
+        // ------
+        // Color reference texture
+        // ------
+        let color_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Color Render Pipeline Layout"),
+                bind_group_layouts: &[&seeds_bind_group_layout, &dimensions_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let color_render_pipeline = create_render_pipeline(
+            "Color Render Pipeline",
+            &device,
+            &color_render_pipeline_layout,
+            MATERIAL_TEXTURE_FORMAT,
+            &[Vertex::desc()],
+            shader!("color.wgsl", "seeds.wgsl"),
+        );
+
+        // ------
+        // Initial Drawing of Seeds
+        // ------
+        let clear_color = wgpu::Color::BLUE;
+
+        let initial_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Initial Render Pipeline Layout"),
+                bind_group_layouts: &[&seeds_bind_group_layout, &dimensions_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let initial_render_pipeline = create_render_pipeline(
+            "Initial Render Pipeline",
+            &device,
+            &initial_render_pipeline_layout,
+            JFA_TEXTURE_FORMAT,
+            &[Vertex::desc()],
+            shader!("seed.wgsl", "seeds.wgsl", "no_seed.wgsl"),
+        );
+
+        // ------
+        // JFA Render Pipeline
+        // ------
+
+        let jfa_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("JFA Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &jfa_bind_group_layout,
+                    &step_bind_group_layout,
+                    &dimensions_bind_group_layout,
+                    &options_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let jfa_render_pipeline = create_render_pipeline(
+            "JFA Render Pipeline",
+            &device,
+            &jfa_render_pipeline_layout,
+            JFA_TEXTURE_FORMAT,
+            &[Vertex::desc()],
+            shader!(
+                "jfa.wgsl",
+                "jfa_common.wgsl",
+                "options.wgsl",
+                "no_seed.wgsl"
+            ),
+        );
+
+        // ------
+        // JFA Compute Pipeline
+        // ------
+
+        let jfa_compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("JFA Compute Pipeline Layout"),
+                bind_group_layouts: &[
+                    &jfa_compute_bind_group_layout,
+                    &step_bind_group_layout,
+                    &dimensions_bind_group_layout,
+                    &options_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let jfa_compute_pipeline = create_compute_pipeline(
+            "JFA Compute Pipeline",
+            &device,
+            &jfa_compute_pipeline_layout,
+            shader!(
+                "jfa_compute.wgsl",
+                "jfa_common.wgsl",
+                "options.wgsl",
+                "no_seed.wgsl"
+            ),
+        );
+
+        // ------
+        // Distance Compute Pipeline
+        // ------
+
+        let distance_compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Distance Compute Pipeline Layout"),
+                bind_group_layouts: &[
+                    &distance_compute_bind_group_layout,
+                    &spread_bind_group_layout,
+                    &dimensions_bind_group_layout,
+                    &options_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let distance_compute_pipeline = create_compute_pipeline(
+            "Distance Compute Pipeline",
+            &device,
+            &distance_compute_pipeline_layout,
+            shader!("distance.wgsl", "options.wgsl", "no_seed.wgsl"),
+        );
+
+        let vertex_buffer = create_vertex_buffer(&device);
+
+        Self {
+            adapter,
+            device,
+            queue,
+            seeds,
+            seeds_bind_group_layout,
+            dimensions_buffer,
+            dimensions_bind_group_layout,
+            dimensions_bind_group,
+            step_buffer,
+            step_bind_group,
+            spread_buffer,
+            spread_bind_group,
+            options_bind_group_layout,
+            options_bind_group,
+            options_buffer,
+            material_sampler,
+            jfa_bind_group_layout,
+            jfa_compute_bind_group_layout,
+            material_bind_group_layout,
+            distance_bind_group_layout,
+            distance_compute_bind_group_layout,
+            textures,
+            color_render_pipeline,
+            initial_render_pipeline,
+            jfa_render_pipeline,
+            jfa_compute_pipeline,
+            distance_compute_pipeline,
+            config: EngineConfig::default(),
+            seed_image: None,
+            material_image: None,
+            vertex_buffer,
+            clear_color,
+            output_is_b: false,
+        }
+    }
+
+    /// The adapter of the device, e.g. to check which formats it supports.
+    pub fn adapter(&self) -> &wgpu::Adapter {
+        &self.adapter
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    pub fn width(&self) -> u32 {
+        self.textures.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.textures.height
+    }
+
+    /// Reallocates the ping-pong and material textures for a new size.
+    ///
+    /// The result of the previous [`Engine::run`] is discarded, so `run`
+    /// has to be called again before the output is read.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 || (width, height) == (self.width(), self.height()) {
+            return;
+        }
+
+        self.textures = Textures::new(
+            &self.device,
+            &TextureLayouts {
+                jfa: &self.jfa_bind_group_layout,
+                jfa_compute: &self.jfa_compute_bind_group_layout,
+                material: &self.material_bind_group_layout,
+                distance: &self.distance_bind_group_layout,
+                distance_compute: &self.distance_compute_bind_group_layout,
+            },
+            &self.material_sampler,
+            width,
+            height,
+        );
+        self.output_is_b = false;
+
+        if let Some((image, texture)) = &mut self.seed_image {
+            *texture = create_seed_image_texture(&self.device, &self.queue, image, width, height);
+        }
+        self.write_material_image();
+
+        self.queue.write_buffer(
+            &self.dimensions_buffer,
+            0,
+            bytemuck::cast_slice(&[width as f32, height as f32]),
+        );
+    }
+
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    /// Changes take effect on the next [`Engine::run`].
+    pub fn config_mut(&mut self) -> &mut EngineConfig {
+        &mut self.config
+    }
+
+    /// Layout of the ping-pong texture bind groups (a `texture_2d<u32>`).
+    pub fn jfa_bind_group_layout(&self) -> &BindGroupLayout {
+        &self.jfa_bind_group_layout
+    }
+
+    /// Layout of the material bind group (a filterable texture and its sampler).
+    pub fn material_bind_group_layout(&self) -> &BindGroupLayout {
+        &self.material_bind_group_layout
+    }
+
+    /// Layout of the distance texture bind group (a non-filterable
+    /// `texture_2d<f32>`).
+    pub fn distance_bind_group_layout(&self) -> &BindGroupLayout {
+        &self.distance_bind_group_layout
+    }
+
+    /// Layout of the `vec2<f32>` uniform holding the texture dimensions.
+    pub fn dimensions_bind_group_layout(&self) -> &BindGroupLayout {
+        &self.dimensions_bind_group_layout
+    }
+
+    pub fn dimensions_bind_group(&self) -> &BindGroup {
+        &self.dimensions_bind_group
+    }
+
+    /// Layout of the uniform matching `Options` in options.wgsl.
+    pub(crate) fn options_bind_group_layout(&self) -> &BindGroupLayout {
+        &self.options_bind_group_layout
+    }
+
+    pub(crate) fn options_bind_group(&self) -> &BindGroup {
+        &self.options_bind_group
+    }
+
+    /// Uploads the options of the current config, before any pass reading them.
+    pub(crate) fn write_options(&self) {
+        self.queue.write_buffer(
+            &self.options_buffer,
+            0,
+            bytemuck::cast_slice(&[JfaOptions::new(&self.config)]),
+        );
+    }
+
+    /// The texture holding the result of the last [`Engine::run`].
+    pub fn output_texture(&self) -> &wgpu::Texture {
+        match self.output_is_b {
+            true => &self.textures.texture_b,
+            false => &self.textures.texture_a,
+        }
+    }
+
+    pub fn output_bind_group(&self) -> &BindGroup {
+        match self.output_is_b {
+            true => &self.textures.texture_b_bind_group,
+            false => &self.textures.texture_a_bind_group,
+        }
+    }
+
+    /// The distance from every pixel to its nearest seed after the last
+    /// [`Engine::run`], measured like [`EngineConfig::distance`], divided by
+    /// [`EngineConfig::spread`] and clamped to 1 if set. Pixels without a
+    /// seed hold the Euclidean length of the diagonal.
+    pub fn distance_texture(&self) -> &wgpu::Texture {
+        &self.textures.distance_texture
+    }
+
+    pub fn distance_bind_group(&self) -> &BindGroup {
+        &self.textures.distance_texture_bind_group
+    }
+
+    pub fn material_texture(&self) -> &wgpu::Texture {
+        &self.textures.material_texture
+    }
+
+    pub fn material_bind_group(&self) -> &BindGroup {
+        &self.textures.material_texture_bind_group
+    }
+
+    pub fn seeds(&self) -> &[Seed] {
+        self.seeds.seeds()
+    }
+
+    /// Adds a seed on top of the existing ones. Ids label the regions, so
+    /// fails without adding it if a seed or primitive has the same id, or
+    /// if its weight isn't positive.
+    pub fn add_seed(&mut self, seed: Seed) -> anyhow::Result<()> {
+        self.seeds.add(seed)
+    }
+
+    /// Removes all seeds and primitives.
+    pub fn clear_seeds(&mut self) {
+        self.seeds.clear();
+    }
+
+    /// Removes the seed with this id, returning it if it existed.
+    pub fn remove_seed(&mut self, id: u32) -> Option<Seed> {
+        self.seeds.remove(id)
+    }
+
+    /// Replaces the seed with the same id. Fails if there is none or if the
+    /// weight isn't positive.
+    pub fn update_seed(&mut self, seed: Seed) -> anyhow::Result<()> {
+        self.seeds.update(seed)
+    }
+
+    pub fn primitives(&self) -> &[Primitive] {
+        self.seeds.primitives()
+    }
+
+    /// Adds a primitive on top of the existing ones and the seed discs.
+    /// Fails without adding it like [`Engine::add_seed`].
+    pub fn add_primitive(&mut self, primitive: Primitive) -> anyhow::Result<()> {
+        self.seeds.add_primitive(primitive)
+    }
+
+    /// Removes the primitive with this id, returning it if it existed.
+    pub fn remove_primitive(&mut self, id: u32) -> Option<Primitive> {
+        self.seeds.remove_primitive(id)
+    }
+
+    /// The ping-pong texture the seed pass draws into, cleared with
+    /// [`NO_SEED_COLOR`].
+    pub(crate) fn seed_texture_view(&self) -> &wgpu::TextureView {
+        &self.textures.texture_a_view
+    }
+
+    pub(crate) fn vertex_buffer(&self) -> &Buffer {
+        &self.vertex_buffer
+    }
+
+    pub fn seed_image(&self) -> Option<&SeedImage> {
+        self.seed_image.as_ref().map(|(image, _)| image)
+    }
+
+    /// Seeds the jump flood from the pixels of an image instead of the
+    /// seed discs, which are then only drawn into the material texture.
+    /// `None` goes back to the discs.
+    pub fn set_seed_image(&mut self, image: Option<SeedImage>) {
+        self.seed_image = image.map(|image| {
+            let texture = create_seed_image_texture(
+                &self.device,
+                &self.queue,
+                &image,
+                self.width(),
+                self.height(),
+            );
+            (image, texture)
+        });
+    }
+
+    pub fn material_image(&self) -> Option<&image::RgbaImage> {
+        self.material_image.as_ref()
+    }
+
+    /// Uses an sRGB image as the material texture instead of drawing the
+    /// seed discs into it, so the output propagates the colour of the
+    /// nearest seed pixel of any picture. The image is anchored at the top
+    /// left corner and cropped or padded with white. `None` goes back to the
+    /// seed discs.
+    pub fn set_material_image(&mut self, image: Option<image::RgbaImage>) {
+        self.material_image = image;
+        self.write_material_image();
+    }
+
+    fn write_material_image(&self) {
+        let Some(image) = &self.material_image else {
+            return;
+        };
+
+        let mut canvas = image::RgbaImage::from_pixel(
+            self.width(),
+            self.height(),
+            image::Rgba([255, 255, 255, 255]),
+        );
+        image::imageops::replace(&mut canvas, image, 0, 0);
+
+        self.queue.write_texture(
+            self.textures.material_texture.as_image_copy(),
+            &canvas,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * self.width()),
+                rows_per_image: Some(self.height()),
+            },
+            self.textures.material_texture.size(),
+        );
+    }
+
+    pub(crate) fn new_encoder(&self) -> CommandEncoder {
+        self.device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            })
+    }
+
+    /// Draws the material and the seeds, then runs the jump flood passes and
+    /// the distance pass. Fails without drawing if the config isn't
+    /// [`EngineConfig::validate`].
+    pub fn run(&mut self) -> anyhow::Result<()> {
+        self.config.validate()?;
+        self.seeds
+            .upload(&self.device, &self.queue, &self.seeds_bind_group_layout);
+        self.write_options();
+
+        let mut encoder = self.new_encoder();
+
+        // A material image is uploaded once instead
+        if self.material_image.is_none() {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.textures.material_texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(&self.color_render_pipeline);
+            render_pass.set_bind_group(0, self.seeds.bind_group(), &[]);
+            render_pass.set_bind_group(1, &self.dimensions_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(0..3, 0..1);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+        // ------
+        // SEED
+        // ------
+
+        let mut encoder = self.new_encoder();
+
+        if let Some((_, texture)) = &self.seed_image {
+            encoder.copy_texture_to_texture(
+                texture.as_image_copy(),
+                self.textures.texture_a.as_image_copy(),
+                texture.size(),
+            );
+        } else {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.textures.texture_a_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(NO_SEED_COLOR),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(&self.initial_render_pipeline);
+            render_pass.set_bind_group(0, self.seeds.bind_group(), &[]);
+            render_pass.set_bind_group(1, &self.dimensions_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(0..3, 0..1);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        self.jump_flood();
+        self.write_distances();
+        Ok(())
+    }
+
+    /// Runs the jump flood passes on the seeds already drawn into
+    /// [`Engine::seed_texture_view`].
+    pub(crate) fn jump_flood(&mut self) {
+        let mut output_is_b = false;
+
+        for step in self.config.step_schedule(self.width(), self.height()) {
+            self.queue
+                .write_buffer(&self.step_buffer, 0, bytemuck::cast_slice(&[step]));
+            let mut encoder = self.new_encoder();
+            match self.config.backend {
+                JfaBackend::Fragment => self.encode_jfa_render_pass(&mut encoder, output_is_b),
+                JfaBackend::Compute => self.encode_jfa_compute_pass(&mut encoder, output_is_b),
+            }
+
+            output_is_b = !output_is_b;
+            self.queue.submit(std::iter::once(encoder.finish()));
+        }
+
+        self.output_is_b = output_is_b;
+    }
+
+    /// Fills the distance texture from the output of the jump flood.
+    pub(crate) fn write_distances(&mut self) {
+        let spread = self.config.spread.unwrap_or(0.);
+        self.queue
+            .write_buffer(&self.spread_buffer, 0, bytemuck::cast_slice(&[spread]));
+
+        let bind_group = match self.output_is_b {
+            true => &self.textures.distance_from_b_bind_group,
+            false => &self.textures.distance_from_a_bind_group,
+        };
+
+        let mut encoder = self.new_encoder();
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Distance Compute Pass"),
+                timestamp_writes: None,
+            });
+
+            compute_pass.set_pipeline(&self.distance_compute_pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.set_bind_group(1, &self.spread_bind_group, &[]);
+            compute_pass.set_bind_group(2, &self.dimensions_bind_group, &[]);
+            compute_pass.set_bind_group(3, &self.options_bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                self.width().div_ceil(WORKGROUP_SIZE),
+                self.height().div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Runs one jump flood step from the ping-pong texture selected by
+    /// `source_is_b` into the other one.
+    fn encode_jfa_render_pass(&self, encoder: &mut CommandEncoder, source_is_b: bool) {
+        let textures = &self.textures;
+        let (source, target) = match source_is_b {
+            true => (&textures.texture_b_bind_group, &textures.texture_a_view),
+            false => (&textures.texture_a_bind_group, &textures.texture_b_view),
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("JFA Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(NO_SEED_COLOR),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.jfa_render_pipeline);
+        render_pass.set_bind_group(0, source, &[]);
+        render_pass.set_bind_group(1, &self.step_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.dimensions_bind_group, &[]);
+        render_pass.set_bind_group(3, &self.options_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Compute equivalent of [`Engine::encode_jfa_render_pass`].
+    fn encode_jfa_compute_pass(&self, encoder: &mut CommandEncoder, source_is_b: bool) {
+        let bind_group = match source_is_b {
+            true => &self.textures.b_to_a_bind_group,
+            false => &self.textures.a_to_b_bind_group,
+        };
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("JFA Compute Pass"),
+            timestamp_writes: None,
+        });
+
+        compute_pass.set_pipeline(&self.jfa_compute_pipeline);
+        compute_pass.set_bind_group(0, bind_group, &[]);
+        compute_pass.set_bind_group(1, &self.step_bind_group, &[]);
+        compute_pass.set_bind_group(2, &self.dimensions_bind_group, &[]);
+        compute_pass.set_bind_group(3, &self.options_bind_group, &[]);
+        compute_pass.dispatch_workgroups(
+            self.width().div_ceil(WORKGROUP_SIZE),
+            self.height().div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+    }
+}
+
+/// Everything that depends on the texture size, recreated on resize.
+struct Textures {
+    width: u32,
+    height: u32,
+    texture_a: wgpu::Texture,
+    texture_b: wgpu::Texture,
+    material_texture: wgpu::Texture,
+    distance_texture: wgpu::Texture,
+    texture_a_view: wgpu::TextureView,
+    texture_b_view: wgpu::TextureView,
+    material_texture_view: wgpu::TextureView,
+    texture_a_bind_group: BindGroup,
+    texture_b_bind_group: BindGroup,
+    a_to_b_bind_group: BindGroup,
+    b_to_a_bind_group: BindGroup,
+    material_texture_bind_group: BindGroup,
+    distance_texture_bind_group: BindGroup,
+    distance_from_a_bind_group: BindGroup,
+    distance_from_b_bind_group: BindGroup,
+}
+
+/// The bind group layouts of everything in [`Textures`].
+struct TextureLayouts<'a> {
+    jfa: &'a BindGroupLayout,
+    jfa_compute: &'a BindGroupLayout,
+    material: &'a BindGroupLayout,
+    distance: &'a BindGroupLayout,
+    distance_compute: &'a BindGroupLayout,
+}
+
+impl Textures {
+    fn new(
+        device: &Device,
+        layouts: &TextureLayouts,
+        sampler: &wgpu::Sampler,
+        width: u32,
+        height: u32,
+    ) -> Textures {
+        let texture_desc = wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: JFA_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+            label: Some("Ping Pong Texture"),
+        };
+
+        let texture_a = device.create_texture(&texture_desc);
+        let texture_b = device.create_texture(&texture_desc);
+        let material_texture = device.create_texture(&wgpu::TextureDescriptor {
+            format: MATERIAL_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            label: Some("Material Texture"),
+            ..texture_desc
+        });
+        let distance_texture = device.create_texture(&wgpu::TextureDescriptor {
+            format: DISTANCE_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            label: Some("Distance Texture"),
+            ..texture_desc
+        });
+
+        let texture_a_view = texture_a.create_view(&Default::default());
+        let texture_b_view = texture_b.create_view(&Default::default());
+        let material_texture_view = material_texture.create_view(&Default::default());
+        let distance_texture_view = distance_texture.create_view(&Default::default());
+
+        let texture_a_bind_group =
+            create_texture_bind_group(device, layouts.jfa, &texture_a_view, "ping_bind_group");
+        let texture_b_bind_group =
+            create_texture_bind_group(device, layouts.jfa, &texture_b_view, "pong_bind_group");
+        let a_to_b_bind_group = create_compute_bind_group(
+            device,
+            layouts.jfa_compute,
+            &texture_a_view,
+            &texture_b_view,
+            "a_to_b_bind_group",
+        );
+        let b_to_a_bind_group = create_compute_bind_group(
+            device,
+            layouts.jfa_compute,
+            &texture_b_view,
+            &texture_a_view,
+            "b_to_a_bind_group",
+        );
+        let material_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: layouts.material,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&material_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("material_bind_group"),
+        });
+        let distance_texture_bind_group = create_texture_bind_group(
+            device,
+            layouts.distance,
+            &distance_texture_view,
+            "distance_bind_group",
+        );
+        let distance_from_a_bind_group = create_compute_bind_group(
+            device,
+            layouts.distance_compute,
+            &texture_a_view,
+            &distance_texture_view,
+            "distance_from_a_bind_group",
+        );
+        let distance_from_b_bind_group = create_compute_bind_group(
+            device,
+            layouts.distance_compute,
+            &texture_b_view,
+            &distance_texture_view,
+            "distance_from_b_bind_group",
+        );
+
+        Self {
+            width,
+            height,
+            texture_a,
+            texture_b,
+            material_texture,
+            distance_texture,
+            texture_a_view,
+            texture_b_view,
+            material_texture_view,
+            texture_a_bind_group,
+            texture_b_bind_group,
+            a_to_b_bind_group,
+            b_to_a_bind_group,
+            material_texture_bind_group,
+            distance_texture_bind_group,
+            distance_from_a_bind_group,
+            distance_from_b_bind_group,
+        }
+    }
+}
+
+pub(crate) fn create_texture_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    view: &wgpu::TextureView,
+    label: &str,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(view),
+        }],
+        label: Some(label),
+    })
+}
+
+/// Binds a texture to read at binding 0 and a storage texture to write at binding 1.
+pub(crate) fn create_compute_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    source: &wgpu::TextureView,
+    target: &wgpu::TextureView,
+    label: &str,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(source),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(target),
+            },
+        ],
+        label: Some(label),
+    })
+}
+
+/// The initial ping-pong texels of a seed image, copied into the seed
+/// texture on every run.
+fn create_seed_image_texture(
+    device: &Device,
+    queue: &Queue,
+    image: &SeedImage,
+    width: u32,
+    height: u32,
+) -> wgpu::Texture {
+    device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: JFA_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+            label: Some("Seed Image Texture"),
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        bytemuck::cast_slice(&image.texels(width, height)),
+    )
+}
+
+pub(crate) fn device_descriptor() -> wgpu::DeviceDescriptor<'static> {
+    wgpu::DeviceDescriptor {
+        required_features: wgpu::Features::empty(),
+        // WebGL doesn't support all of wgpu's features, so if
+        // we're building for the web, we'll have to disable some.
+        required_limits: if cfg!(target_arch = "wasm32") {
+            wgpu::Limits::downlevel_webgl2_defaults()
+        } else {
+            wgpu::Limits::default()
+        },
+        label: None,
+        memory_hints: Default::default(),
+    }
+}
+
+pub(crate) fn create_vertex_buffer(device: &wgpu::Device) -> Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(VERTICES),
+        usage: wgpu::BufferUsages::VERTEX,
+    })
+}
+
+pub(crate) fn create_render_pipeline(
+    label: &str,
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    vertex_layouts: &[wgpu::VertexBufferLayout],
+    shader: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(shader);
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: vertex_layouts,
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                // Integer targets like the ping-pong textures can't be blended
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        cache: None,
+        multiview: None,
+    })
+}
+
+pub(crate) fn create_compute_pipeline(
+    label: &str,
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: wgpu::ShaderModuleDescriptor,
+) -> wgpu::ComputePipeline {
+    let shader = device.create_shader_module(shader);
+
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        module: &shader,
+        entry_point: Some("cs_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    })
+}
+
+pub(crate) fn create_uniform_bind_group_layout(
+    device: &wgpu::Device,
+    label: &str,
+    visibility: wgpu::ShaderStages,
+) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+        label: Some(label),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pollster::FutureExt;
+
+    #[test]
+    fn step_schedule_halves_down_to_one() {
+        let config = EngineConfig::default();
+        assert_eq!(config.step_schedule(64, 48), [32, 16, 8, 4, 2, 1]);
+        assert_eq!(config.step_schedule(100, 3), [64, 32, 16, 8, 4, 2, 1]);
+        assert_eq!(config.step_schedule(1, 1), [1]);
+    }
+
+    #[test]
+    fn step_schedule_overrides() {
+        let config = EngineConfig {
+            start_step: Some(8),
+            iterations: Some(6),
+            ..Default::default()
+        };
+        assert_eq!(config.step_schedule(64, 64), [8, 4, 2, 1, 1, 1]);
+
+        let config = EngineConfig {
+            iterations: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(config.step_schedule(64, 64), [32, 16]);
+    }
+
+    #[test]
+    fn step_schedule_variants() {
+        let schedule = |variant| {
+            EngineConfig {
+                variant,
+                ..Default::default()
+            }
+            .step_schedule(16, 16)
+        };
+
+        assert_eq!(schedule(JfaVariant::OnePlusJfa), [1, 8, 4, 2, 1]);
+        assert_eq!(schedule(JfaVariant::JfaPlusOne), [8, 4, 2, 1, 1]);
+        assert_eq!(schedule(JfaVariant::JfaPlusTwo), [8, 4, 2, 1, 2, 1]);
+        assert_eq!(
+            schedule(JfaVariant::Custom {
+                pre: vec![0, 3],
+                post: vec![4],
+            }),
+            [1, 3, 8, 4, 2, 1, 4]
+        );
+    }
+
+    #[test]
+    fn metric_lengths() {
+        let delta = [3., -4.];
+        assert_eq!(Metric::Euclidean.length(delta), 5.);
+        assert_eq!(Metric::Manhattan.length(delta), 7.);
+        assert_eq!(Metric::Chebyshev.length(delta), 4.);
+        assert_eq!(Metric::Minkowski(1.).length(delta), 7.);
+        assert!((Metric::Minkowski(2.).length(delta) - 5.).abs() < 1e-5);
+        assert!((Metric::Minkowski(16.).length(delta) - 4.).abs() < 1e-2);
+    }
+
+    #[test]
+    fn minkowski_exponent_is_at_least_one() {
+        assert!(Metric::Minkowski(1.).validate().is_ok());
+        assert!(Metric::Minkowski(0.5).validate().is_err());
+        assert!(Metric::Minkowski(-1.).validate().is_err());
+        assert!(Metric::Minkowski(f32::INFINITY).validate().is_err());
+        assert!(Metric::Minkowski(f32::NAN).validate().is_err());
+    }
+
+    #[test]
+    fn run_rejects_invalid_configs() {
+        let mut engine = Engine::headless(8, 8, true).block_on().unwrap();
+        engine.config_mut().metric = Metric::Minkowski(0.5);
+        assert!(engine.run().is_err());
+
+        engine.config_mut().metric = Metric::Minkowski(1.5);
+        assert!(engine.run().is_ok());
+    }
+
+    #[test]
+    fn distance_wraps() {
+        let config = EngineConfig {
+            wrap: true,
+            ..Default::default()
+        };
+        assert_eq!(config.distance([0., 0.], [9., 0.], 10, 10), 1.);
+        assert_eq!(config.distance([0., 0.], [5., 0.], 10, 10), 5.);
+    }
+}
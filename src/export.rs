@@ -0,0 +1,434 @@
+//! Writes the results of the passes to image files.
+
+use crate::engine::{Engine, NO_SEED};
+use crate::sdf::SignedDistanceField;
+use anyhow::{bail, Context};
+use exr::prelude::{AnyChannel, AnyChannels, FlatSamples, Image, WritableImage};
+use image::{ImageBuffer, ImageFormat, Luma, Rgba};
+use std::path::Path;
+
+/// How exported values are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// PNG with 8 bits per channel.
+    #[default]
+    Png8,
+    /// PNG with 16 bits per channel.
+    Png16,
+    /// OpenEXR with 32-bit channels, lossless.
+    Exr,
+    /// Headerless little-endian 32-bit values, row-major with the channels
+    /// of a pixel next to each other, lossless.
+    Raw,
+}
+
+impl Engine {
+    /// Writes the material colour of every pixel's nearest seed, the colour
+    /// map shown in the window.
+    ///
+    /// PNGs store the sRGB colours, the float formats linear RGBA.
+    pub async fn export_colors(
+        &self,
+        path: impl AsRef<Path>,
+        format: ExportFormat,
+    ) -> anyhow::Result<()> {
+        let colors = self.read_colors().await?;
+        let values = colors.iter().flat_map(|color| match format {
+            ExportFormat::Png8 | ExportFormat::Png16 => color.map(|c| c as f32 / 255.),
+            ExportFormat::Exr | ExportFormat::Raw => {
+                let [r, g, b, a] = color.map(|c| c as f32 / 255.);
+                [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a]
+            }
+        });
+
+        write_floats(
+            path.as_ref(),
+            self.width(),
+            self.height(),
+            &["R", "G", "B", "A"],
+            &values.collect::<Vec<_>>(),
+            format,
+        )
+    }
+
+    /// Writes the region label (seed id) of every pixel, [`NO_SEED`] where
+    /// no seed was found.
+    ///
+    /// 8-bit PNGs store the id as packed RGBA, the inverse of
+    /// [`crate::image_seeds::SeedImage`], and 16-bit PNGs as grey levels,
+    /// which fails for ids of `u16::MAX` and above. The float formats store
+    /// 32-bit unsigned integers.
+    pub async fn export_labels(
+        &self,
+        path: impl AsRef<Path>,
+        format: ExportFormat,
+    ) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let (width, height) = (self.width(), self.height());
+        let labels: Vec<u32> = self
+            .read_labels()
+            .await?
+            .iter()
+            .map(|label| label.unwrap_or(NO_SEED))
+            .collect();
+
+        match format {
+            ExportFormat::Png8 => {
+                let bytes = labels.iter().flat_map(|id| id.to_le_bytes()).collect();
+                save_png(
+                    path,
+                    ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, bytes),
+                )
+            }
+            ExportFormat::Png16 => {
+                let levels = labels
+                    .iter()
+                    .map(|&id| match id {
+                        NO_SEED => Ok(u16::MAX),
+                        id => u16::try_from(id)
+                            .ok()
+                            .filter(|&level| level != u16::MAX)
+                            .with_context(|| format!("seed id {id} doesn't fit in 16 bits")),
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                save_png(
+                    path,
+                    ImageBuffer::<Luma<u16>, _>::from_raw(width, height, levels),
+                )
+            }
+            ExportFormat::Exr => {
+                write_exr(path, width, height, vec![("id", FlatSamples::U32(labels))])
+            }
+            ExportFormat::Raw => write_raw(path, bytemuck::cast_slice(&labels)),
+        }
+    }
+
+    /// Writes [`Engine::distance_texture`].
+    ///
+    /// PNGs store distances up to [`crate::engine::EngineConfig::spread`],
+    /// or the largest distance without one, as 0 to 1.
+    pub async fn export_distances(
+        &self,
+        path: impl AsRef<Path>,
+        format: ExportFormat,
+    ) -> anyhow::Result<()> {
+        let mut distances = self.read_distance_texture().await?;
+
+        // With a spread the texture is already normalised
+        if self.config().spread.is_none()
+            && matches!(format, ExportFormat::Png8 | ExportFormat::Png16)
+        {
+            let max = distances.iter().copied().fold(0., f32::max);
+            if max > 0. {
+                distances.iter_mut().for_each(|distance| *distance /= max);
+            }
+        }
+
+        write_floats(
+            path.as_ref(),
+            self.width(),
+            self.height(),
+            &["Y"],
+            &distances,
+            format,
+        )
+    }
+}
+
+impl SignedDistanceField {
+    /// Writes the signed distances of the last
+    /// [`SignedDistanceField::generate`].
+    ///
+    /// PNGs store distances between minus and plus the
+    /// [`crate::engine::EngineConfig::spread`], or the largest absolute
+    /// distance without one, as 0 to 1 with the edge at 0.5.
+    pub async fn export(
+        &self,
+        engine: &Engine,
+        path: impl AsRef<Path>,
+        format: ExportFormat,
+    ) -> anyhow::Result<()> {
+        let mut distances = self.read(engine).await?;
+
+        if matches!(format, ExportFormat::Png8 | ExportFormat::Png16) {
+            let range = engine
+                .config()
+                .spread
+                .unwrap_or_else(|| distances.iter().map(|d| d.abs()).fold(0., f32::max));
+            if range > 0. {
+                distances
+                    .iter_mut()
+                    .for_each(|distance| *distance = 0.5 + 0.5 * *distance / range);
+            }
+        }
+
+        write_floats(
+            path.as_ref(),
+            engine.width(),
+            engine.height(),
+            &["Y"],
+            &distances,
+            format,
+        )
+    }
+}
+
+/// Writes interleaved values with one or four channels. PNGs quantize the
+/// values clamped to 0 to 1.
+fn write_floats(
+    path: &Path,
+    width: u32,
+    height: u32,
+    channels: &[&str],
+    values: &[f32],
+    format: ExportFormat,
+) -> anyhow::Result<()> {
+    let quantize = |max: f32| {
+        values
+            .iter()
+            .map(move |value| (value.clamp(0., 1.) * max).round())
+    };
+
+    match (format, channels.len()) {
+        (ExportFormat::Png8, 1) => {
+            let levels = quantize(255.).map(|value| value as u8).collect();
+            save_png(
+                path,
+                ImageBuffer::<Luma<u8>, _>::from_raw(width, height, levels),
+            )
+        }
+        (ExportFormat::Png8, 4) => {
+            let levels = quantize(255.).map(|value| value as u8).collect();
+            save_png(
+                path,
+                ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, levels),
+            )
+        }
+        (ExportFormat::Png16, 1) => {
+            let levels = quantize(65535.).map(|value| value as u16).collect();
+            save_png(
+                path,
+                ImageBuffer::<Luma<u16>, _>::from_raw(width, height, levels),
+            )
+        }
+        (ExportFormat::Png16, 4) => {
+            let levels = quantize(65535.).map(|value| value as u16).collect();
+            save_png(
+                path,
+                ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, levels),
+            )
+        }
+        (ExportFormat::Png8 | ExportFormat::Png16, count) => {
+            bail!("PNGs can't store {count} channels")
+        }
+        (ExportFormat::Exr, count) => {
+            let channels = channels
+                .iter()
+                .enumerate()
+                .map(|(channel, name)| {
+                    let samples = values.iter().skip(channel).step_by(count).copied();
+                    (*name, FlatSamples::F32(samples.collect()))
+                })
+                .collect();
+            write_exr(path, width, height, channels)
+        }
+        (ExportFormat::Raw, _) => write_raw(path, bytemuck::cast_slice(values)),
+    }
+}
+
+fn save_png<P>(path: &Path, image: Option<ImageBuffer<P, Vec<P::Subpixel>>>) -> anyhow::Result<()>
+where
+    P: image::PixelWithColorType,
+    [P::Subpixel]: image::EncodableLayout,
+{
+    image
+        .context("image data doesn't match its size")?
+        .save_with_format(path, ImageFormat::Png)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn write_exr(
+    path: &Path,
+    width: u32,
+    height: u32,
+    channels: Vec<(&str, FlatSamples)>,
+) -> anyhow::Result<()> {
+    let channels = channels
+        .into_iter()
+        .map(|(name, samples)| AnyChannel::new(name, samples))
+        .collect();
+    Image::from_channels(
+        (width as usize, height as usize),
+        AnyChannels::sort(channels),
+    )
+    .write()
+    .to_file(path)
+    .with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn write_raw(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    // The targets wgpu supports are little-endian
+    std::fs::write(path, bytes).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeds::Seed;
+    use pollster::FutureExt;
+    use std::path::PathBuf;
+
+    fn engine(ids: [u32; 2]) -> Engine {
+        let mut engine = Engine::headless(16, 8, true).block_on().unwrap();
+        engine
+            .add_seed(Seed::new(ids[0], [3., 3.], 1., [1., 0.5, 0., 1.]))
+            .unwrap();
+        engine
+            .add_seed(Seed::new(ids[1], [12., 4.], 2., [0., 0.25, 1., 1.]))
+            .unwrap();
+        engine.run().unwrap();
+        engine
+    }
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn file_name(format: ExportFormat) -> String {
+        let extension = match format {
+            ExportFormat::Png8 | ExportFormat::Png16 => "png",
+            ExportFormat::Exr => "exr",
+            ExportFormat::Raw => "raw",
+        };
+        format!("{format:?}.{extension}")
+    }
+
+    /// The samples of the only channel of an EXR file.
+    fn read_exr(path: &Path) -> FlatSamples {
+        let image = exr::prelude::read_first_flat_layer_from_file(path).unwrap();
+        image.layer_data.channel_data.list[0].sample_data.clone()
+    }
+
+    #[test]
+    fn labels_round_trip() {
+        let engine = engine([1, 2]);
+        let labels: Vec<u32> = (engine.read_labels().block_on().unwrap())
+            .iter()
+            .map(|label| label.unwrap_or(NO_SEED))
+            .collect();
+        let directory = directory("export-labels");
+
+        for format in [
+            ExportFormat::Png8,
+            ExportFormat::Png16,
+            ExportFormat::Exr,
+            ExportFormat::Raw,
+        ] {
+            let path = directory.join(file_name(format));
+            engine.export_labels(&path, format).block_on().unwrap();
+
+            let read: Vec<u32> = match format {
+                ExportFormat::Png8 => image::open(&path)
+                    .unwrap()
+                    .to_rgba8()
+                    .pixels()
+                    .map(|pixel| u32::from_le_bytes(pixel.0))
+                    .collect(),
+                ExportFormat::Png16 => image::open(&path)
+                    .unwrap()
+                    .into_luma16()
+                    .pixels()
+                    .map(|pixel| pixel.0[0] as u32)
+                    .collect(),
+                ExportFormat::Exr => match read_exr(&path) {
+                    FlatSamples::U32(ids) => ids,
+                    samples => panic!("ids stored as {samples:?}"),
+                },
+                ExportFormat::Raw => bytemuck::pod_collect_to_vec(&std::fs::read(&path).unwrap()),
+            };
+            assert_eq!(read, labels, "{format:?}");
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn distances_round_trip() {
+        let engine = engine([1, 2]);
+        let distances = engine.read_distance_texture().block_on().unwrap();
+        let max = distances.iter().copied().fold(0., f32::max);
+        let directory = directory("export-distances");
+
+        for format in [
+            ExportFormat::Png8,
+            ExportFormat::Png16,
+            ExportFormat::Exr,
+            ExportFormat::Raw,
+        ] {
+            let path = directory.join(file_name(format));
+            engine.export_distances(&path, format).block_on().unwrap();
+
+            // PNGs are normalised by the largest distance and quantized
+            let (read, scale, tolerance): (Vec<f32>, f32, f32) = match format {
+                ExportFormat::Png8 => {
+                    let image = image::open(&path).unwrap().into_luma8();
+                    let levels = image.pixels().map(|pixel| pixel.0[0] as f32 / 255.);
+                    (levels.collect(), max, 0.5 / 255.)
+                }
+                ExportFormat::Png16 => {
+                    let image = image::open(&path).unwrap().into_luma16();
+                    let levels = image.pixels().map(|pixel| pixel.0[0] as f32 / 65535.);
+                    (levels.collect(), max, 0.5 / 65535.)
+                }
+                ExportFormat::Exr => match read_exr(&path) {
+                    FlatSamples::F32(distances) => (distances, 1., 0.),
+                    samples => panic!("distances stored as {samples:?}"),
+                },
+                ExportFormat::Raw => {
+                    let bytes = std::fs::read(&path).unwrap();
+                    (bytemuck::pod_collect_to_vec(&bytes), 1., 0.)
+                }
+            };
+            assert_eq!(read.len(), distances.len());
+            for (read, distance) in read.iter().zip(&distances) {
+                assert!(
+                    (read - distance / scale).abs() <= tolerance + f32::EPSILON,
+                    "{format:?} stored {distance} as {read}"
+                );
+            }
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn labels_that_overflow_16_bits() {
+        let directory = directory("export-overflow");
+        let path = directory.join("labels.png");
+
+        for id in [u16::MAX as u32, 70_000] {
+            let error = engine([1, id])
+                .export_labels(&path, ExportFormat::Png16)
+                .block_on()
+                .unwrap_err();
+            assert!(error.to_string().contains("16 bits"), "{error}");
+        }
+        // The formats with 32-bit channels keep them
+        engine([1, 70_000])
+            .export_labels(&path, ExportFormat::Png8)
+            .block_on()
+            .unwrap();
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
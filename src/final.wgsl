@@ -19,31 +19,51 @@ fn vs_main(
 }
 
 @group(0) @binding(0)
-var t_jfa: texture_2d<f32>;
-@group(0) @binding(1)
-var s_jfa: sampler;
+var t_jfa: texture_2d<u32>;
 
 @group(1) @binding(0)
 var t_material: texture_2d<f32>;
 @group(1) @binding(1)
 var s_material: sampler;
 
+@group(2) @binding(0)
+var<uniform> dimensions: vec2<f32>;
+
+// 0: material colour of the nearest seed, 1: colour per region label,
+// 2: fluence, 3: path-traced fluence
+@group(3) @binding(0)
+var<uniform> view: u32;
+@group(3) @binding(1)
+var t_fluence: texture_2d<f32>;
+// The sum of the path-traced radiance in rgb and the number of rays in alpha
+@group(3) @binding(2)
+var t_accumulator: texture_2d<f32>;
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let pos = vec2<f32>((in.pos.x + 1) / 2, (in.pos.y - 1) / -2);
-    // return textureSample(t_jfa, s_jfa, pos);
-    // return textureSample(t_material, s_material, pos);
-    return textureSample(t_material, s_material, tex_coords(decode_coords(textureSample(t_jfa, s_jfa, pos))));
-}
-
-fn decode_coords(color: vec4<f32>) -> vec2<f32> {
-    return vec2<f32>(decode_data(color.rg), decode_data(color.ba));
+    if view == 2u {
+        return vec4<f32>(textureLoad(t_fluence, vec2<i32>(in.clip_position.xy), 0).rgb, 1.);
+    }
+    if view == 3u {
+        let accumulated = textureLoad(t_accumulator, vec2<i32>(in.clip_position.xy), 0);
+        return vec4<f32>(accumulated.rgb / max(accumulated.a, 1.), 1.);
+    }
+    let closest = textureLoad(t_jfa, vec2<i32>(in.clip_position.xy), 0);
+    if all(closest.xy == NO_SEED) {
+        return vec4<f32>(0., 0., 0., 1.);
+    }
+    if view == 1u {
+        return label_color(closest.z);
+    }
+    return textureSampleLevel(t_material, s_material, tex_coords(closest.xy), 0.);
 }
 
-fn decode_data(encoded: vec2<f32>) -> f32 {
-    return encoded.x * 65025 + encoded.y * 255;
+// An arbitrary but stable colour per seed id
+fn label_color(id: u32) -> vec4<f32> {
+    return vec4<f32>(unpack4x8unorm(hash(id)).rgb, 1.);
 }
 
-fn tex_coords(coords: vec2<f32>) -> vec2<f32> {
-    return coords/2048; // hardcoded dimensions
+// Samples the center of the seed's pixel
+fn tex_coords(coords: vec2<u32>) -> vec2<f32> {
+    return (vec2<f32>(coords) + 0.5) / dimensions;
 }
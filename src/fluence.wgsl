@@ -0,0 +1,49 @@
+@group(0) @binding(0)
+var t_distance: texture_2d<f32>;
+@group(0) @binding(1)
+var t_material: texture_2d<f32>;
+
+// The merged first cascade. Shares the layout of cascade.wgsl.
+@group(1) @binding(0)
+var t_probes: texture_2d<f32>;
+@group(1) @binding(1)
+var t_fluence: texture_storage_2d<rgba16float, write>;
+
+@group(2) @binding(0)
+var<uniform> dimensions: vec2<f32>;
+
+// The radiance arriving at every pixel from all directions, averaged over
+// the four directions of the nearest probes of the first cascade. Surface
+// pixels are lit by their own colour, every ray from them hits at once.
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= vec2<u32>(dimensions)) {
+        return;
+    }
+
+    if textureLoad(t_distance, id.xy, 0).r < 0.5 {
+        textureStore(t_fluence, id.xy, vec4<f32>(textureLoad(t_material, id.xy, 0).rgb, 1.));
+        return;
+    }
+
+    // Probes inside a surface only see that surface, so they are left out
+    // unless all of them are
+    let pos = vec2<f32>(id.xy) + 0.5;
+    var radiance = vec4<f32>(0.);
+    var fallback = vec4<f32>(0.);
+    for (var i = 0u; i < 4u; i++) {
+        let corner = probe_corner(pos, cascade.spacing, i);
+        let probe = probe_directions(corner.probe, 2u, 0u);
+        fallback += corner.weight * probe;
+        let pixel = vec2<u32>(probe_position(corner.probe, cascade.spacing));
+        if textureLoad(t_distance, pixel, 0).r >= 0.5 {
+            radiance += vec4<f32>(corner.weight * probe.rgb, corner.weight);
+        }
+    }
+    if radiance.a > 0. {
+        radiance /= radiance.a;
+    } else {
+        radiance = fallback;
+    }
+    textureStore(t_fluence, id.xy, vec4<f32>(radiance.rgb, 1.));
+}
@@ -0,0 +1,132 @@
+//! Seeds taken from the pixels of an image instead of [`crate::seeds::Seed`] discs.
+
+use crate::engine::NO_SEED;
+use anyhow::Context;
+use std::path::Path;
+
+/// Which pixels of an image become seeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SeedPredicate {
+    /// Every pixel that is neither white nor fully transparent.
+    NonWhite,
+    /// Every pixel with an alpha above the threshold.
+    AlphaAbove(u8),
+    /// Every pixel of exactly this RGBA colour.
+    Color([u8; 4]),
+}
+
+impl SeedPredicate {
+    pub fn matches(self, pixel: [u8; 4]) -> bool {
+        match self {
+            SeedPredicate::NonWhite => pixel[3] > 0 && pixel[..3] != [255; 3],
+            SeedPredicate::AlphaAbove(threshold) => pixel[3] > threshold,
+            SeedPredicate::Color(color) => pixel == color,
+        }
+    }
+}
+
+/// Seed pixels of an image: every matching pixel is a seed at its own
+/// coordinate, with its packed RGBA colour as id so regions can be told
+/// apart by colour.
+#[derive(Debug, Clone)]
+pub struct SeedImage {
+    width: u32,
+    height: u32,
+    /// The packed colour of every seed pixel, row-major
+    ids: Vec<Option<u32>>,
+}
+
+impl SeedImage {
+    pub fn new(image: &image::RgbaImage, predicate: SeedPredicate) -> SeedImage {
+        let ids = image
+            .pixels()
+            .map(|pixel| {
+                predicate
+                    .matches(pixel.0)
+                    .then(|| u32::from_le_bytes(pixel.0))
+            })
+            .collect();
+
+        Self {
+            width: image.width(),
+            height: image.height(),
+            ids,
+        }
+    }
+
+    /// Loads a PNG or JPEG file.
+    pub fn open(path: impl AsRef<Path>, predicate: SeedPredicate) -> anyhow::Result<SeedImage> {
+        let path = path.as_ref();
+        let image = image::open(path)
+            .with_context(|| format!("failed to load seed image {}", path.display()))?;
+        Ok(Self::new(&image.to_rgba8(), predicate))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn seed_count(&self) -> usize {
+        self.ids.iter().flatten().count()
+    }
+
+    /// The initial ping-pong texels for a texture of the given size. The
+    /// image is anchored at the top left corner and cropped, pixels it doesn't
+    /// cover have no seed.
+    pub(crate) fn texels(&self, width: u32, height: u32) -> Vec<[u32; 4]> {
+        let mut texels = vec![[NO_SEED, NO_SEED, 0, 0]; (width * height) as usize];
+
+        for y in 0..height.min(self.height) {
+            for x in 0..width.min(self.width) {
+                if let Some(id) = self.ids[(y * self.width + x) as usize] {
+                    texels[(y * width + x) as usize] = [x, y, id, 1f32.to_bits()];
+                }
+            }
+        }
+
+        texels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn predicates() {
+        assert!(SeedPredicate::NonWhite.matches([0, 0, 0, 255]));
+        assert!(!SeedPredicate::NonWhite.matches([255, 255, 255, 255]));
+        assert!(!SeedPredicate::NonWhite.matches([0, 0, 0, 0]));
+        assert!(SeedPredicate::AlphaAbove(10).matches([255, 255, 255, 11]));
+        assert!(!SeedPredicate::AlphaAbove(10).matches([0, 0, 0, 10]));
+        assert!(SeedPredicate::Color([1, 2, 3, 4]).matches([1, 2, 3, 4]));
+        assert!(!SeedPredicate::Color([1, 2, 3, 4]).matches([1, 2, 3, 5]));
+    }
+
+    #[test]
+    fn texels_are_cropped_and_padded() {
+        let mut image = image::RgbaImage::from_pixel(3, 2, image::Rgba([255; 4]));
+        image.put_pixel(0, 0, image::Rgba([1, 0, 0, 255]));
+        image.put_pixel(2, 1, image::Rgba([2, 0, 0, 255]));
+        let seeds = SeedImage::new(&image, SeedPredicate::NonWhite);
+        assert_eq!(seeds.seed_count(), 2);
+
+        let no_seed = [NO_SEED, NO_SEED, 0, 0];
+        let first = [0, 0, u32::from_le_bytes([1, 0, 0, 255]), 1f32.to_bits()];
+
+        // Cropped to the first column, padded with a row
+        assert_eq!(seeds.texels(1, 3), [first, no_seed, no_seed]);
+
+        let texels = seeds.texels(4, 2);
+        assert_eq!(texels[0], first);
+        assert_eq!(
+            texels[4 + 2],
+            [2, 1, u32::from_le_bytes([2, 0, 0, 255]), 1f32.to_bits()]
+        );
+        assert_eq!(texels.iter().filter(|texel| **texel != no_seed).count(), 2);
+    }
+}
@@ -1,91 +1,55 @@
+use crate::cascades::{CascadeConfig, RadianceCascades};
+use crate::engine::{self, create_render_pipeline, Engine, JfaBackend, JfaVariant, Metric, Vertex};
+use crate::path_tracer::PathTracer;
+use crate::scene::Scene;
+use crate::seeds::Seed;
+use anyhow::Context;
+use std::path::PathBuf;
 use std::sync::Arc;
-use wgpu::{
-    self,
-    util::{DeviceExt, RenderEncoder},
-    BindGroup, Buffer, CommandEncoder, Device, Queue, RenderPipeline, Surface,
-    SurfaceConfiguration, TextureView,
-};
+use wgpu::{self, util::DeviceExt, BindGroup, Buffer, Surface, SurfaceConfiguration};
 use winit::{
-    event::{ElementState, KeyEvent, MouseButton, WindowEvent},
+    event::{ElementState, KeyEvent, WindowEvent},
     keyboard::{KeyCode::*, PhysicalKey},
     window::Window,
 };
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct Vertex {
-    position: [f32; 2],
-}
-
-impl Vertex {
-    fn desc() -> wgpu::VertexBufferLayout<'static> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[wgpu::VertexAttribute {
-                offset: 0,
-                shader_location: 0,
-                format: wgpu::VertexFormat::Float32x2,
-            }],
-        }
-    }
+/// What the final pass shows. Matches `view` in final.wgsl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    /// The material colour of each pixel's nearest seed.
+    Material = 0,
+    /// A distinct colour per region label (seed id).
+    Labels = 1,
+    /// The light reaching every pixel from the seeds and primitives, see
+    /// [`RadianceCascades`].
+    Fluence = 2,
+    /// The fluence converging over frames while nothing changes, see
+    /// [`PathTracer`].
+    Reference = 3,
 }
 
-const VERTICES: &[Vertex] = &[
-    Vertex {
-        position: [-1., 1.],
-    },
-    Vertex {
-        position: [-1., -3.],
-    },
-    Vertex { position: [3., 1.] },
-];
-
-// We need this for Rust to store our data correctly for the shaders
-#[repr(C)]
-// This is so we can store this in a buffer
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct MouseUniform {
-    pos: [f32; 2],
-}
-
-macro_rules! shader {
-    ($name:expr) => {
-        wgpu::ShaderModuleDescriptor {
-            label: Some($name),
-            source: wgpu::ShaderSource::Wgsl(include_str!($name).into()),
-        }
-    };
-}
+/// Rays per pixel the reference view adds every frame.
+const REFERENCE_SAMPLES_PER_FRAME: u32 = 16;
 
 pub struct State {
     surface: Surface<'static>,
-    device: Device,
-    queue: Queue,
+    engine: Engine,
     config: SurfaceConfiguration,
-    mouse_uniform: MouseUniform,
-    mouse_buffer: Buffer,
-    mouse_bind_group: BindGroup,
-    dimensions_buffer: Buffer,
-    dimensions_bind_group: BindGroup,
-    step_bind_group: BindGroup,
-    step_buffer: Buffer,
     pub size: winit::dpi::PhysicalSize<u32>,
-    texture_a: wgpu::Texture,
-    texture_b: wgpu::Texture,
-    material_texture: wgpu::Texture,
-    texture_a_view: wgpu::TextureView,
-    texture_b_view: wgpu::TextureView,
-    material_texture_view: wgpu::TextureView,
-    texture_a_bind_group: BindGroup,
-    texture_b_bind_group: BindGroup,
-    material_texture_bind_group: BindGroup,
-    color_render_pipeline: wgpu::RenderPipeline,
-    initial_render_pipeline: wgpu::RenderPipeline,
-    jfa_render_pipeline: wgpu::RenderPipeline,
     final_render_pipeline: wgpu::RenderPipeline,
+    view: View,
+    view_buffer: Buffer,
+    view_bind_group_layout: wgpu::BindGroupLayout,
+    view_bind_group: BindGroup,
+    cascades: RadianceCascades,
+    path_tracer: PathTracer,
     vertex_buffer: Buffer,
     clear_color: wgpu::Color,
+    scene: Scene,
+    scene_path: PathBuf,
+    // The seed following the cursor, with an id no scene seed or primitive
+    // uses
+    mouse_seed_id: u32,
     // The window must be declared after the surface so
     // it gets dropped after it as the surface contains
     // unsafe references to the window's resources.
@@ -94,7 +58,8 @@ pub struct State {
 
 impl State {
     // Creating some of the wgpu types requires async code
-    pub async fn new(window: Window) -> State {
+    /// Shows the scene, which is saved back to `scene_path` with S.
+    pub async fn new(window: Window, scene: Scene, scene_path: PathBuf) -> anyhow::Result<State> {
         let size = window.inner_size();
 
         let window = Arc::new(window);
@@ -109,7 +74,7 @@ impl State {
             ..Default::default()
         });
 
-        let surface = instance.create_surface(Arc::clone(&window)).unwrap();
+        let surface = instance.create_surface(Arc::clone(&window))?;
 
         let adapter = match instance
             .request_adapter(&wgpu::RequestAdapterOptions {
@@ -120,37 +85,17 @@ impl State {
             .await
         {
             Some(adapter) => adapter,
-            None => {
-                instance
-                    .enumerate_adapters(wgpu::Backends::all())
-                    .into_iter()
-                    .filter(|adapter| {
-                        // Check if this adapter supports our surface
-                        adapter.is_surface_supported(&surface)
-                    })
-                    .next()
-                    .unwrap()
-            }
+            None => instance
+                .enumerate_adapters(wgpu::Backends::all())
+                .into_iter()
+                // Check if this adapter supports our surface
+                .find(|adapter| adapter.is_surface_supported(&surface))
+                .context("no graphics adapter can draw to the window")?,
         };
 
         let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
-                    // WebGL doesn't support all of wgpu's features, so if
-                    // we're building for the web, we'll have to disable some.
-                    required_limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
-                    label: None,
-                    memory_hints: Default::default(),
-                },
-                None, // Trace path
-            )
-            .await
-            .unwrap();
+            .request_device(&engine::device_descriptor(), None)
+            .await?;
 
         let surface_caps = surface.get_capabilities(&adapter);
 
@@ -177,494 +122,277 @@ impl State {
 
         surface.configure(&device, &config);
 
-        // ------
-        // Unfiforms/Buffers and Bind Groups for the initial render
-        // ------
-        let mouse_uniform = MouseUniform { pos: [0., 0.] };
-
-        let mouse_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Mouse Buffer"),
-            contents: bytemuck::cast_slice(&[mouse_uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let mouse_bind_group_layout = create_uniform_bind_group_layout(
-            &device,
-            "Mouse BGL",
-            wgpu::ShaderStages::VERTEX_FRAGMENT,
-        );
-
-        let mouse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &mouse_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: mouse_buffer.as_entire_binding(),
-            }],
-            label: Some("mouse_bind_group"),
-        });
-
-        let dimensions_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Mouse Buffer"),
-            contents: bytemuck::cast_slice(&[size.width as f32, size.height as f32]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        let mut engine = Engine::new(adapter, device, queue, size.width, size.height);
+
+        scene.apply(&mut engine)?;
+        // The window may not have the size of the scene
+        engine.resize(size.width, size.height);
+        let mouse_seed_id = (0..)
+            .find(|id| {
+                engine.seeds().iter().all(|seed| seed.id != *id)
+                    && engine
+                        .primitives()
+                        .iter()
+                        .all(|primitive| primitive.id != *id)
+            })
+            .context("every seed id is used")?;
+        engine.add_seed(mouse_seed(mouse_seed_id, [0., 0.]))?;
 
-        let dimensions_bind_group_layout = create_uniform_bind_group_layout(
-            &device,
-            "Dimensions BGL",
-            wgpu::ShaderStages::VERTEX_FRAGMENT,
-        );
-
-        let dimensions_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &dimensions_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: dimensions_buffer.as_entire_binding(),
-            }],
-            label: Some("dimensions_bind_group"),
-        });
+        let device = engine.device();
 
         // ------
-        // Ping Pong Textures
+        // Final Drawing to Window
         // ------
-        let texture_desc = wgpu::TextureDescriptor {
-            size: wgpu::Extent3d {
-                width: size.width,
-                height: size.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
-            label: Some("Ping Pong Texture"),
-        };
+        let view = View::Material;
 
-        let texture_a = device.create_texture(&texture_desc);
-        let texture_b = device.create_texture(&texture_desc);
-        let material_texture = device.create_texture(&texture_desc);
-
-        let texture_a_view = texture_a.create_view(&Default::default());
-        let texture_b_view = texture_b.create_view(&Default::default());
-        let material_texture_view = material_texture.create_view(&Default::default());
-
-        let jfa_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge, // NOTE: Could be interesting to repeat
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
+        let view_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("View Buffer"),
+            contents: bytemuck::cast_slice(&[view as u32]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        let jfa_bind_group_layout =
+        let cascades = RadianceCascades::new(&engine, CascadeConfig::default());
+        let path_tracer = PathTracer::new(&engine, REFERENCE_SAMPLES_PER_FRAME);
+
+        // The view and the fluence it may show
+        let view_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
                     wgpu::BindGroupLayoutEntry {
                         binding: 0,
                         visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Texture {
                             multisampled: false,
                             view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
                         },
                         count: None,
                     },
                     wgpu::BindGroupLayoutEntry {
-                        binding: 1,
+                        binding: 2,
                         visibility: wgpu::ShaderStages::FRAGMENT,
-                        // This should match the filterable field of the
-                        // corresponding Texture entry above.
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
                         count: None,
                     },
                 ],
-                label: Some("sdf_bind_group_layout"),
+                label: Some("view_bind_group_layout"),
             });
 
-        let texture_a_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &jfa_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_a_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&jfa_sampler),
-                },
-            ],
-            label: Some("ping_bind_group"),
-        });
-
-        let texture_b_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &jfa_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_b_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&jfa_sampler),
-                },
-            ],
-            label: Some("pong_bind_group"),
-        });
-        let material_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &jfa_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&material_texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&jfa_sampler),
-                },
-            ],
-            label: Some("pong_bind_group"),
-        });
-
-        // ------
-        // Step Bind Group Layout
-        // ------
-        let step_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Step Buffer"),
-            contents: bytemuck::cast_slice(&[0f32]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let step_bind_group_layout = create_uniform_bind_group_layout(
-            &device,
-            "step_bind_group_layout",
-            wgpu::ShaderStages::FRAGMENT,
+        let view_bind_group = create_view_bind_group(
+            device,
+            &view_bind_group_layout,
+            &view_buffer,
+            &cascades,
+            &path_tracer,
         );
 
-        let step_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &step_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: step_buffer.as_entire_binding(),
-            }],
-            label: Some("step_bind_group"),
-        });
-
-        // ------
-        // Color reference texture
-        // ------
-        let clear_color = wgpu::Color::BLUE;
-
-        let color_render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Color Render Pipeline Layout"),
-                bind_group_layouts: &[&mouse_bind_group_layout, &dimensions_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        let color_render_pipeline = {
-            create_render_pipeline(
-                "Color Render Pipeline",
-                &device,
-                &color_render_pipeline_layout,
-                wgpu::TextureFormat::Rgba8Unorm,
-                None,
-                &[Vertex::desc()],
-                shader!("color.wgsl"),
-                wgpu::PrimitiveTopology::TriangleList,
-            )
-        };
-
-        // ------
-        // Initial Drawing of Seeds
-        // ------
-        let clear_color = wgpu::Color::BLUE;
-
-        let initial_render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Initial Render Pipeline Layout"),
-                bind_group_layouts: &[&mouse_bind_group_layout, &dimensions_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        let initial_render_pipeline = {
-            create_render_pipeline(
-                "Initial Render Pipeline",
-                &device,
-                &initial_render_pipeline_layout,
-                wgpu::TextureFormat::Rgba8Unorm,
-                None,
-                &[Vertex::desc()],
-                shader!("seed.wgsl"),
-                wgpu::PrimitiveTopology::TriangleList,
-            )
-        };
-
-        // ------
-        // JFA Render Pipeline
-        // ------
-
-        let jfa_render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("JFA Render Pipeline Layout"),
-                bind_group_layouts: &[&jfa_bind_group_layout, &step_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        let jfa_render_pipeline = {
-            create_render_pipeline(
-                "JFA Render Pipeline",
-                &device,
-                &jfa_render_pipeline_layout,
-                wgpu::TextureFormat::Rgba8Unorm,
-                None,
-                &[Vertex::desc()],
-                shader!("jfa.wgsl"),
-                wgpu::PrimitiveTopology::TriangleList,
-            )
-        };
-
-        // ------
-        // Final Drawing to Window
-        // ------
-
         let final_render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Final Render Pipeline Layout"),
-                bind_group_layouts: &[&jfa_bind_group_layout, &jfa_bind_group_layout],
+                bind_group_layouts: &[
+                    engine.jfa_bind_group_layout(),
+                    engine.material_bind_group_layout(),
+                    engine.dimensions_bind_group_layout(),
+                    &view_bind_group_layout,
+                ],
                 push_constant_ranges: &[],
             });
 
-        let final_render_pipeline = {
-            create_render_pipeline(
-                "Final Render Pipeline",
-                &device,
-                &final_render_pipeline_layout,
-                config.format,
-                None,
-                &[Vertex::desc()],
-                shader!("final.wgsl"),
-                wgpu::PrimitiveTopology::TriangleList,
-            )
-        };
+        let final_render_pipeline = create_render_pipeline(
+            "Final Render Pipeline",
+            device,
+            &final_render_pipeline_layout,
+            config.format,
+            &[Vertex::desc()],
+            shader!("final.wgsl", "no_seed.wgsl", "hash.wgsl"),
+        );
 
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let vertex_buffer = engine::create_vertex_buffer(device);
 
-        Self {
+        Ok(Self {
             surface,
-            device,
-            queue,
+            engine,
             config,
-            mouse_uniform,
-            mouse_buffer,
-            mouse_bind_group,
-            dimensions_buffer,
-            dimensions_bind_group,
-            step_buffer,
-            step_bind_group,
             size,
-            texture_a,
-            texture_b,
-            material_texture,
-            texture_a_view,
-            texture_b_view,
-            material_texture_view,
-            texture_a_bind_group,
-            texture_b_bind_group,
-            material_texture_bind_group,
-            color_render_pipeline,
-            initial_render_pipeline,
-            jfa_render_pipeline,
             final_render_pipeline,
+            view,
+            view_buffer,
+            view_bind_group_layout,
+            view_bind_group,
+            cascades,
+            path_tracer,
             vertex_buffer,
-            clear_color,
+            clear_color: wgpu::Color::BLUE,
+            scene,
+            scene_path,
+            mouse_seed_id,
             window,
-        }
+        })
     }
 
     pub fn window(&self) -> &Window {
         self.window.as_ref()
     }
 
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        // BUG: Resize doesn't really work for now
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
 
             self.config.width = new_size.width;
             self.config.height = new_size.height;
 
-            self.queue.write_buffer(
-                &self.dimensions_buffer,
-                0,
-                bytemuck::cast_slice(&[new_size.width as f32, new_size.height as f32]),
+            self.surface.configure(self.engine.device(), &self.config);
+            self.engine.resize(new_size.width, new_size.height);
+            self.cascades.resize(&self.engine);
+            self.path_tracer.resize(&self.engine);
+            self.view_bind_group = create_view_bind_group(
+                self.engine.device(),
+                &self.view_bind_group_layout,
+                &self.view_buffer,
+                &self.cascades,
+                &self.path_tracer,
             );
-
-            let mut pixel_coords = Vec::with_capacity((new_size.width * new_size.height) as usize);
-            for y in 0..new_size.height {
-                for x in 0..new_size.width {
-                    pixel_coords.push([x as f32, y as f32]);
-                }
-            }
-
-            self.surface.configure(&self.device, &self.config);
         }
     }
 
     pub fn input(&mut self, event: &WindowEvent) {
-        // println!("{:?}", event);
         match event {
-            WindowEvent::KeyboardInput { event, .. } => {
-                let pressed = match event.state == ElementState::Pressed {
-                    true => 1.0,
-                    false => 0.0,
-                };
-
-                match event.physical_key {
-                    PhysicalKey::Code(key) => match key {
-                        KeyT => {}
-                        _ => (),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        physical_key: PhysicalKey::Code(key),
+                        ..
                     },
-                    _ => (),
+                ..
+            } => {
+                let config = self.engine.config_mut();
+                match key {
+                    KeyB => {
+                        config.backend = match config.backend {
+                            JfaBackend::Fragment => JfaBackend::Compute,
+                            JfaBackend::Compute => JfaBackend::Fragment,
+                        };
+                        log::info!("JFA backend: {:?}", config.backend);
+                    }
+                    KeyV => {
+                        config.variant = match config.variant {
+                            JfaVariant::Jfa => JfaVariant::OnePlusJfa,
+                            JfaVariant::OnePlusJfa => JfaVariant::JfaPlusOne,
+                            JfaVariant::JfaPlusOne => JfaVariant::JfaPlusTwo,
+                            _ => JfaVariant::Jfa,
+                        };
+                        log::info!("JFA variant: {:?}", config.variant);
+                    }
+                    KeyM => {
+                        config.metric = match config.metric {
+                            Metric::Euclidean => Metric::Manhattan,
+                            Metric::Manhattan => Metric::Chebyshev,
+                            Metric::Chebyshev => Metric::Minkowski(3.),
+                            Metric::Minkowski(_) => Metric::Euclidean,
+                        };
+                        log::info!("Metric: {:?}", config.metric);
+                    }
+                    KeyW => {
+                        config.wrap = !config.wrap;
+                        log::info!("Wrap around edges: {}", config.wrap);
+                    }
+                    KeyL => self.set_view(match self.view {
+                        View::Labels => View::Material,
+                        _ => View::Labels,
+                    }),
+                    KeyG => self.set_view(match self.view {
+                        View::Fluence => View::Material,
+                        _ => View::Fluence,
+                    }),
+                    KeyP => self.set_view(match self.view {
+                        View::Reference => View::Material,
+                        _ => View::Reference,
+                    }),
+                    KeyS => {
+                        match self.save_scene() {
+                            Ok(()) => log::info!("Saved scene to {}", self.scene_path.display()),
+                            Err(error) => log::error!("{error:#}"),
+                        }
+                        return;
+                    }
+                    _ => return,
                 }
+                self.update();
             }
-            WindowEvent::MouseInput {
-                state,
-                button: MouseButton::Left,
-                ..
-            } => {}
             WindowEvent::CursorMoved { position, .. } => {
-                self.mouse_uniform.pos[0] = position.x as f32;
-                self.mouse_uniform.pos[1] = position.y as f32;
-
-                self.queue.write_buffer(
-                    &self.mouse_buffer,
-                    0,
-                    bytemuck::cast_slice(&[self.mouse_uniform]),
-                );
+                let seed = mouse_seed(self.mouse_seed_id, [position.x as f32, position.y as f32]);
+                if let Err(error) = self.engine.update_seed(seed) {
+                    log::error!("{error:#}");
+                }
                 self.update();
             }
             _ => (),
         }
     }
 
-    pub fn update(&mut self) {
-        self.window().request_redraw();
+    /// Writes the current seeds and settings to the scene file, without the
+    /// seed following the cursor.
+    pub fn save_scene(&mut self) -> anyhow::Result<()> {
+        self.scene.capture(&self.engine);
+        // No other seed has its id
+        self.scene
+            .seeds
+            .retain(|seed| seed.id != self.mouse_seed_id);
+        self.scene.save(&self.scene_path)
     }
 
-    fn new_encoder(&self) -> CommandEncoder {
-        self.device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            })
+    fn set_view(&mut self, view: View) {
+        self.view = view;
+        self.engine.queue().write_buffer(
+            &self.view_buffer,
+            0,
+            bytemuck::cast_slice(&[self.view as u32]),
+        );
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let mut encoder = self.new_encoder();
-
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.material_texture_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(self.clear_color),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-
-            render_pass.set_pipeline(&self.color_render_pipeline);
-            render_pass.set_bind_group(0, &self.mouse_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.dimensions_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.draw(0..3, 0..1);
-        }
-        self.queue.submit(std::iter::once(encoder.finish()));
-        // ------
-        // SEED
-        // ------
-
-        let mut encoder = self.new_encoder();
-
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.texture_a_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(self.clear_color),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+    /// Redraws after the seeds, settings or view changed.
+    pub fn update(&mut self) {
+        self.path_tracer.reset();
+        self.window().request_redraw();
+    }
 
-            render_pass.set_pipeline(&self.initial_render_pipeline);
-            render_pass.set_bind_group(0, &self.mouse_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.dimensions_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.draw(0..3, 0..1);
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        // The scene was validated and the keys only pick valid settings
+        if let Err(error) = self.engine.run() {
+            log::error!("{error:#}");
         }
-        self.queue.submit(std::iter::once(encoder.finish()));
-
-        // ------
-        // JFA
-        // ------
-
-        let mut step = 2000f32;
-
-        let mut ping = (&self.texture_a_bind_group, &self.texture_a_view);
-        let mut pong = (&self.texture_b_bind_group, &self.texture_b_view);
-
-        let mut i = 0;
-        while i < 10 {
-            step = step / 2.;
-            self.queue
-                .write_buffer(&self.step_buffer, 0, bytemuck::cast_slice(&[step]));
-            let mut encoder = self.new_encoder();
-            {
-                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("JFA Pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: pong.1,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(self.clear_color),
-                            store: wgpu::StoreOp::Store,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                    occlusion_query_set: None,
-                    timestamp_writes: None,
-                });
-
-                render_pass.set_pipeline(&self.jfa_render_pipeline);
-                render_pass.set_bind_group(0, ping.0, &[]);
-                render_pass.set_bind_group(1, &self.step_bind_group, &[]);
-                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-                render_pass.draw(0..3, 0..1);
+        // Overwrites the nearest seeds, which this view doesn't show
+        match self.view {
+            View::Fluence => self.cascades.render(&mut self.engine),
+            View::Reference => {
+                self.path_tracer.render(&mut self.engine);
+                // Keeps adding samples until the scene changes
+                self.window().request_redraw();
             }
-
-            let tmp = ping;
-            ping = pong;
-            pong = tmp;
-            i += 1;
-            self.queue.submit(std::iter::once(encoder.finish()));
+            View::Material | View::Labels => (),
         }
 
         // ------
@@ -675,7 +403,7 @@ impl State {
         let view = output // NOTE: Does this need to be recreated every time?
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = self.new_encoder();
+        let mut encoder = self.engine.new_encoder();
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
@@ -693,98 +421,68 @@ impl State {
             });
 
             render_pass.set_pipeline(&self.final_render_pipeline);
-            render_pass.set_bind_group(0, ping.0, &[]);
-            render_pass.set_bind_group(1, &self.material_texture_bind_group, &[]);
+            render_pass.set_bind_group(0, self.engine.output_bind_group(), &[]);
+            render_pass.set_bind_group(1, self.engine.material_bind_group(), &[]);
+            render_pass.set_bind_group(2, self.engine.dimensions_bind_group(), &[]);
+            render_pass.set_bind_group(3, &self.view_bind_group, &[]);
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
             render_pass.draw(0..3, 0..1);
         }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
+        self.engine
+            .queue()
+            .submit(std::iter::once(encoder.finish()));
         output.present();
 
         Ok(())
     }
 }
 
-fn create_render_pipeline(
-    label: &str,
+fn create_view_bind_group(
     device: &wgpu::Device,
-    layout: &wgpu::PipelineLayout,
-    color_format: wgpu::TextureFormat,
-    depth_format: Option<wgpu::TextureFormat>,
-    vertex_layouts: &[wgpu::VertexBufferLayout],
-    shader: wgpu::ShaderModuleDescriptor,
-    topology: wgpu::PrimitiveTopology,
-) -> wgpu::RenderPipeline {
-    let shader = device.create_shader_module(shader);
-
-    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some(label),
-        layout: Some(layout),
-        vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: Some("vs_main"),
-            buffers: vertex_layouts,
-            compilation_options: Default::default(),
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: Some("fs_main"),
-            targets: &[Some(wgpu::ColorTargetState {
-                format: color_format,
-                blend: Some(wgpu::BlendState {
-                    alpha: wgpu::BlendComponent::REPLACE,
-                    color: wgpu::BlendComponent::REPLACE,
-                }),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-            compilation_options: Default::default(),
-        }),
-        primitive: wgpu::PrimitiveState {
-            topology,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-            polygon_mode: wgpu::PolygonMode::Fill,
-            // Requires Features::DEPTH_CLIP_CONTROL
-            unclipped_depth: false,
-            // Requires Features::CONSERVATIVE_RASTERIZATION
-            conservative: false,
-        },
-        depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
-            format,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
-        multisample: wgpu::MultisampleState {
-            count: 1,
-            mask: !0,
-            alpha_to_coverage_enabled: false,
-        },
-        cache: None,
-        multiview: None,
+    layout: &wgpu::BindGroupLayout,
+    view_buffer: &Buffer,
+    cascades: &RadianceCascades,
+    path_tracer: &PathTracer,
+) -> BindGroup {
+    let fluence_view = cascades.fluence_texture().create_view(&Default::default());
+    let accumulator_view = path_tracer
+        .accumulator_texture()
+        .create_view(&Default::default());
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: view_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&fluence_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&accumulator_view),
+            },
+        ],
+        label: Some("view_bind_group"),
     })
 }
 
-fn create_uniform_bind_group_layout(
-    device: &wgpu::Device,
-    label: &str,
-    visibility: wgpu::ShaderStages,
-) -> wgpu::BindGroupLayout {
-    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
-        label: Some(label),
-    })
+/// Three seeds at positions relative to the canvas size, with radii relative
+/// to its smallest side.
+pub fn demo_scene(width: u32, height: u32) -> Scene {
+    let mut scene = Scene::new(width, height);
+    let (width, height) = (width as f32, height as f32);
+    let radius = 0.02 * width.min(height);
+    scene.seeds = vec![
+        Seed::new(1, [0.1 * width, 0.1 * height], radius, [0., 1., 0., 1.]),
+        Seed::new(2, [0.2 * width, 0.5 * height], radius, [0., 0., 1., 1.]),
+        Seed::new(3, [0.9 * width, 0.7 * height], radius, [1., 0., 0., 1.]),
+    ];
+    scene
+}
+
+fn mouse_seed(id: u32, position: [f32; 2]) -> Seed {
+    Seed::new(id, position, 40., [0.4, 0.7, 0., 1.])
 }
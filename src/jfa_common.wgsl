@@ -0,0 +1,72 @@
+// Shared by the fragment (jfa.wgsl) and compute (jfa_compute.wgsl) jump flood passes
+
+@group(0) @binding(0)
+var t_jfa: texture_2d<u32>;
+
+@group(1) @binding(0)
+var<uniform> step: u32;
+
+@group(2) @binding(0)
+var<uniform> dimensions: vec2<f32>;
+
+// Closest seed among the texel itself and its 8 neighbours `step` pixels away.
+// Texels hold the seed's pixel in xy, its id in z and the bits of its weight in w.
+fn jump_flood(pos: vec2<i32>) -> vec4<u32> {
+    let offset = i32(step);
+
+    var closest = textureLoad(t_jfa, pos, 0);
+
+    closest = compare_point_with_offset(pos, closest, pos + vec2<i32>(-offset, -offset));
+    closest = compare_point_with_offset(pos, closest, pos + vec2<i32>(0, -offset));
+    closest = compare_point_with_offset(pos, closest, pos + vec2<i32>(offset, -offset));
+    closest = compare_point_with_offset(pos, closest, pos + vec2<i32>(-offset, 0));
+    closest = compare_point_with_offset(pos, closest, pos + vec2<i32>(offset, 0));
+    closest = compare_point_with_offset(pos, closest, pos + vec2<i32>(-offset, offset));
+    closest = compare_point_with_offset(pos, closest, pos + vec2<i32>(0, offset));
+    closest = compare_point_with_offset(pos, closest, pos + vec2<i32>(offset, offset));
+
+    return closest;
+}
+
+fn compare_point_with_offset(pos: vec2<i32>, current: vec4<u32>, offset_pos: vec2<i32>) -> vec4<u32> {
+    let new_pos = wrap_pixel(offset_pos);
+    if any(new_pos < vec2<i32>(0)) || any(new_pos >= vec2<i32>(dimensions)) {
+        return current;
+    }
+
+    let other = textureLoad(t_jfa, new_pos, 0);
+
+    if all(other.xy == NO_SEED) {
+        return current;
+    }
+    if all(current.xy == NO_SEED) || weighted_distance(pos, other) < weighted_distance(pos, current) {
+        return other;
+    }
+    return current;
+}
+
+// The distance to the seed of a texel, adjusted by its weight. Matches
+// `Weighting::apply` in engine.rs.
+fn weighted_distance(pos: vec2<i32>, texel: vec4<u32>) -> f32 {
+    let d = seed_distance(pos, texel.xy);
+    let weight = bitcast<f32>(texel.w);
+
+    switch options.weighting {
+        case 1u: {
+            return d - weight;
+        }
+        case 2u: {
+            return d / weight;
+        }
+        case 3u: {
+            return d * d - weight;
+        }
+        default: {
+            return d;
+        }
+    }
+}
+
+fn seed_distance(pos: vec2<i32>, seed: vec2<u32>) -> f32 {
+    return pixel_distance(vec2<f32>(pos), vec2<f32>(seed));
+}
@@ -0,0 +1,13 @@
+// The texture written by this step, the other ping-pong texture is read through t_jfa
+@group(0) @binding(1)
+var t_out: texture_storage_2d<rgba32uint, write>;
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= vec2<u32>(dimensions)) {
+        return;
+    }
+
+    let closest = jump_flood(vec2<i32>(id.xy));
+    textureStore(t_out, id.xy, closest);
+}
@@ -0,0 +1,27 @@
+/// Builds a shader module from a WGSL file, optionally prefixed with other
+/// WGSL files it depends on (WGSL has no includes of its own).
+macro_rules! shader {
+    ($name:expr $(, $include:expr)*) => {
+        wgpu::ShaderModuleDescriptor {
+            label: Some($name),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!($(include_str!($include), "\n",)* include_str!($name)).into(),
+            ),
+        }
+    };
+}
+
+pub mod batch;
+pub mod cascades;
+pub mod engine;
+pub mod export;
+pub mod image_seeds;
+pub mod jfa;
+pub mod path_tracer;
+pub mod primitives;
+pub mod raymarch;
+pub mod readback;
+pub mod reference;
+pub mod scene;
+pub mod sdf;
+pub mod seeds;
@@ -1,6 +1,8 @@
-pub mod jfa;
-use crate::jfa::State;
 use pollster::FutureExt;
+use radiance_cascades::batch::{self, Batch};
+use radiance_cascades::jfa::{self, State};
+use radiance_cascades::scene::Scene;
+use std::path::PathBuf;
 use winit::application::ApplicationHandler;
 use winit::dpi::PhysicalSize;
 use winit::event::{
@@ -14,20 +16,33 @@ use winit::window::{Window, WindowId};
 
 pub struct App {
     state: Option<State>,
+    scene: Option<Scene>,
+    scene_path: PathBuf,
+    // Why the window couldn't be set up, reported once the event loop exits
+    error: Option<anyhow::Error>,
 }
 
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(scene) = self.scene.take() else {
+            return;
+        };
         let window_attributes = Window::default_attributes()
             .with_title("Window 1")
-            .with_inner_size(PhysicalSize::new(2048, 2048))
+            .with_inner_size(PhysicalSize::new(scene.width, scene.height))
             .with_cursor(winit::window::CursorIcon::Wait);
 
         let window = event_loop.create_window(window_attributes).unwrap();
-        self.state = Some(State::new(window).block_on());
+        match State::new(window, scene, self.scene_path.clone()).block_on() {
+            Ok(state) => self.state = Some(state),
+            Err(error) => {
+                self.error = Some(error);
+                event_loop.exit();
+            }
+        }
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
         match event {
             CloseRequested
             | KeyboardInput {
@@ -66,7 +81,7 @@ impl ApplicationHandler for App {
                 }
             }
             CursorEntered { .. } => {
-                if let Some(state) = self.state.as_mut() {
+                if let Some(_state) = self.state.as_mut() {
                     // if let Err(e) = state.window().set_cursor_grab(CursorGrabMode::Locked) {
                     //     println!("Error setting cursor grab: {e}");
                     // }
@@ -85,7 +100,7 @@ impl ApplicationHandler for App {
         &mut self,
         _event_loop: &ActiveEventLoop,
         _device_id: winit::event::DeviceId,
-        event: DeviceEvent,
+        _event: DeviceEvent,
     ) {
         // match event {
         //     DeviceEvent::MouseMotion { delta } => {
@@ -101,6 +116,20 @@ impl ApplicationHandler for App {
 
 fn main() {
     // std::env::set_var("RUST_BACKTRACE", "1");
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or("warn,radiance_cascades=info"),
+    )
+    .init();
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("batch") {
+        let result = Batch::parse(args.skip(1)).and_then(|batch| batch.run().block_on());
+        if let Err(e) = result {
+            eprintln!("error: {:#}\n\n{}", e, batch::USAGE);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let event_loop = EventLoop::new().unwrap();
 
     // ControlFlow::Wait pauses the event loop if no events are available to process.
@@ -108,8 +137,34 @@ fn main() {
     // input, and uses significantly less power/CPU time than ControlFlow::Poll.
     event_loop.set_control_flow(ControlFlow::Wait);
 
-    let mut app = App { state: None };
+    // The scene file given as the first argument is loaded if it exists,
+    // and is where the scene is saved to
+    let scene_path = std::env::args_os()
+        .nth(1)
+        .map_or_else(|| PathBuf::from("scene.ron"), PathBuf::from);
+    let scene = if scene_path.exists() {
+        match Scene::open(&scene_path) {
+            Ok(scene) => scene,
+            Err(e) => {
+                eprintln!("error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        jfa::demo_scene(2048, 2048)
+    };
+
+    let mut app = App {
+        state: None,
+        scene: Some(scene),
+        scene_path,
+        error: None,
+    };
     if let Err(e) = event_loop.run_app(&mut app) {
         println!("{:?}", e);
     }
+    if let Some(e) = app.error {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,21 @@
+// Shared by the boundary seed (sdf_seed.wgsl) and combine (sdf.wgsl) passes
+
+// 1 inside the shape, 0 outside
+@group(1) @binding(0)
+var t_mask: texture_2d<u32>;
+
+// The side whose distances are computed by this run, 1 inside and 0 outside
+@group(1) @binding(1)
+var<uniform> side: u32;
+
+@group(2) @binding(0)
+var<uniform> dimensions: vec2<f32>;
+
+// Without wrapping, pixels outside the texture count as outside the shape
+fn mask(offset_pos: vec2<i32>) -> u32 {
+    let pos = wrap_pixel(offset_pos);
+    if any(pos < vec2<i32>(0)) || any(pos >= vec2<i32>(dimensions)) {
+        return 0u;
+    }
+    return textureLoad(t_mask, pos, 0).r;
+}
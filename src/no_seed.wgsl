@@ -0,0 +1,2 @@
+// Marks a texel without a nearest seed, matches `NO_SEED` in engine.rs
+const NO_SEED = vec2<u32>(0xffffffffu);
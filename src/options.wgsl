@@ -0,0 +1,53 @@
+// Runtime settings shared by every pass that compares distances between pixels
+
+// Matches `JfaOptions` in engine.rs
+struct Options {
+    // 1 if the domain wraps around its edges like a torus
+    wrap: u32,
+    // 0 Euclidean, 1 Manhattan, 2 Chebyshev, 3 Minkowski with exponent `p`,
+    // which `Engine::run` and `SignedDistanceField::generate` check is at
+    // least 1
+    metric: u32,
+    p: f32,
+    // 0 none, 1 additive, 2 multiplicative, 3 power
+    weighting: u32,
+}
+
+@group(3) @binding(0)
+var<uniform> options: Options;
+
+// The pixel itself or, when wrapping, its copy inside the texture. Pixels
+// outside the texture are left for the caller to reject without wrapping.
+fn wrap_pixel(pos: vec2<i32>) -> vec2<i32> {
+    if options.wrap == 0u {
+        return pos;
+    }
+    // GLSL leaves `%` undefined for negative operands, so pixels before the
+    // texture wrap by how far they are before it
+    let size = vec2<u32>(dimensions);
+    let after = vec2<u32>(max(pos, vec2<i32>(0))) % size;
+    let before = (size - vec2<u32>(max(-pos, vec2<i32>(0))) % size) % size;
+    return vec2<i32>(select(after, before, pos < vec2<i32>(0)));
+}
+
+fn pixel_distance(a: vec2<f32>, b: vec2<f32>) -> f32 {
+    var delta = abs(a - b);
+    if options.wrap != 0u {
+        delta = min(delta, dimensions - delta);
+    }
+
+    switch options.metric {
+        case 1u: {
+            return delta.x + delta.y;
+        }
+        case 2u: {
+            return max(delta.x, delta.y);
+        }
+        case 3u: {
+            return pow(pow(delta.x, options.p) + pow(delta.y, options.p), 1. / options.p);
+        }
+        default: {
+            return length(delta);
+        }
+    }
+}
@@ -0,0 +1,56 @@
+@group(0) @binding(0)
+var t_distance: texture_2d<f32>;
+@group(0) @binding(1)
+var t_material: texture_2d<f32>;
+
+// The accumulator of the previous frame and the one being written
+@group(1) @binding(0)
+var t_previous: texture_2d<f32>;
+@group(1) @binding(1)
+var t_accumulator: texture_storage_2d<rgba32float, write>;
+
+@group(2) @binding(0)
+var<uniform> dimensions: vec2<f32>;
+
+// Matches `FrameUniform` in path_tracer.rs
+struct Frame {
+    // Frames accumulated so far, 0 starts over
+    index: u32,
+    samples: u32,
+}
+
+@group(3) @binding(0)
+var<uniform> frame: Frame;
+
+const TAU = 6.283185307179586;
+
+// Adds `frame.samples` rays in uniformly random directions from random
+// points in every pixel to the sum of their radiance in rgb and their count
+// in alpha
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= vec2<u32>(dimensions)) {
+        return;
+    }
+
+    var state = hash(id.x + hash(id.y + hash(frame.index)));
+    var sum = vec3<f32>(0.);
+    for (var i = 0u; i < frame.samples; i++) {
+        let origin = vec2<f32>(id.xy) + vec2<f32>(random(&state), random(&state));
+        let angle = TAU * random(&state);
+        let hit = ray_march(origin, vec2<f32>(cos(angle), sin(angle)), length(dimensions));
+        sum += hit.material.rgb;
+    }
+
+    var accumulated = vec4<f32>(sum, f32(frame.samples));
+    if frame.index > 0u {
+        accumulated += textureLoad(t_previous, id.xy, 0);
+    }
+    textureStore(t_accumulator, id.xy, accumulated);
+}
+
+// Uniform in [0, 1)
+fn random(state: ptr<function, u32>) -> f32 {
+    *state = hash(*state);
+    return f32(*state >> 8u) / 16777216.;
+}
@@ -0,0 +1,370 @@
+//! A progressive Monte Carlo reference for the lighting of
+//! [`crate::cascades::RadianceCascades`].
+
+use crate::engine::{
+    self, create_compute_bind_group, create_compute_pipeline, Engine, WORKGROUP_SIZE,
+};
+use crate::raymarch::{create_scene_bind_group, create_scene_bind_group_layout, SurfaceField};
+use wgpu::{self, BindGroup, BindGroupLayout, Buffer, Device};
+
+/// Format of the accumulator textures.
+pub const ACCUMULATOR_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+/// The frame being accumulated, matches `Frame` in path_trace.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct FrameUniform {
+    index: u32,
+    samples: u32,
+}
+
+/// Averages the radiance of random rays from random points of every pixel
+/// over many frames, converging to the fluence the radiance cascades
+/// approximate.
+///
+/// Lights the same scene: surfaces that are neither white nor transparent
+/// emit their colour and block the light behind them. Surfaces don't
+/// reflect, so every path is a single ray through a [`SurfaceField`].
+///
+/// The scene is captured on the first frame after a
+/// [`PathTracer::reset`], which overwrites the engine's nearest-seed and
+/// distance outputs. Later frames only add samples.
+pub struct PathTracer {
+    samples_per_frame: u32,
+    frame: u32,
+    frame_buffer: Buffer,
+    frame_bind_group: BindGroup,
+    scene_bind_group_layout: BindGroupLayout,
+    accumulator_bind_group_layout: BindGroupLayout,
+    textures: PathTracerTextures,
+    surface: SurfaceField,
+    compute_pipeline: wgpu::ComputePipeline,
+}
+
+impl PathTracer {
+    /// Traces `samples_per_frame` rays per pixel on every
+    /// [`PathTracer::render`].
+    pub fn new(engine: &Engine, samples_per_frame: u32) -> PathTracer {
+        let device = engine.device();
+
+        let frame_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Buffer"),
+            size: std::mem::size_of::<FrameUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let frame_bind_group_layout = engine::create_uniform_bind_group_layout(
+            device,
+            "frame_bind_group_layout",
+            wgpu::ShaderStages::COMPUTE,
+        );
+
+        let frame_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &frame_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: frame_buffer.as_entire_binding(),
+            }],
+            label: Some("frame_bind_group"),
+        });
+
+        let scene_bind_group_layout = create_scene_bind_group_layout(device);
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        };
+
+        // The previous frame and the one being written
+        let accumulator_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    texture_entry(0),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: ACCUMULATOR_TEXTURE_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("accumulator_bind_group_layout"),
+            });
+
+        let textures = PathTracerTextures::new(
+            device,
+            &scene_bind_group_layout,
+            &accumulator_bind_group_layout,
+            engine.width(),
+            engine.height(),
+        );
+
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Path Trace Compute Pipeline Layout"),
+                bind_group_layouts: &[
+                    &scene_bind_group_layout,
+                    &accumulator_bind_group_layout,
+                    engine.dimensions_bind_group_layout(),
+                    &frame_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let compute_pipeline = create_compute_pipeline(
+            "Path Trace Compute Pipeline",
+            device,
+            &compute_pipeline_layout,
+            shader!("path_trace.wgsl", "raymarch.wgsl", "hash.wgsl"),
+        );
+
+        Self {
+            samples_per_frame,
+            frame: 0,
+            frame_buffer,
+            frame_bind_group,
+            scene_bind_group_layout,
+            accumulator_bind_group_layout,
+            textures,
+            surface: SurfaceField::new(engine),
+            compute_pipeline,
+        }
+    }
+
+    /// The number of frames accumulated since the last reset.
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// The number of rays per pixel accumulated since the last reset.
+    pub fn sample_count(&self) -> u32 {
+        self.frame * self.samples_per_frame
+    }
+
+    /// Discards the accumulated samples, to be called whenever the scene
+    /// changes. The next [`PathTracer::render`] captures the scene again.
+    pub fn reset(&mut self) {
+        self.frame = 0;
+    }
+
+    /// The sum of the radiance of all rays through a pixel in rgb and their
+    /// count in alpha, in [`ACCUMULATOR_TEXTURE_FORMAT`]. Recreated when the
+    /// engine size changes, see [`PathTracer::resize`].
+    pub fn accumulator_texture(&self) -> &wgpu::Texture {
+        &self.textures.accumulator_texture
+    }
+
+    /// Reallocates the textures for the size of the engine and starts over,
+    /// if the size changed.
+    pub fn resize(&mut self, engine: &Engine) {
+        if (engine.width(), engine.height()) == (self.textures.width, self.textures.height) {
+            return;
+        }
+
+        self.textures = PathTracerTextures::new(
+            engine.device(),
+            &self.scene_bind_group_layout,
+            &self.accumulator_bind_group_layout,
+            engine.width(),
+            engine.height(),
+        );
+        self.reset();
+    }
+
+    /// Adds a frame of samples, to be called after [`PathTracer::resize`]
+    /// and the [`Engine::run`] that writes the scene's material texture.
+    pub fn render(&mut self, engine: &mut Engine) {
+        // The engine's textures change with every run, so the scene is kept
+        // until the next reset
+        if self.frame == 0 {
+            self.surface.generate(engine);
+            let mut encoder = engine.new_encoder();
+            for (source, target) in [
+                (engine.distance_texture(), &self.textures.distance_texture),
+                (engine.material_texture(), &self.textures.material_texture),
+            ] {
+                encoder.copy_texture_to_texture(
+                    source.as_image_copy(),
+                    target.as_image_copy(),
+                    source.size(),
+                );
+            }
+            engine.queue().submit(std::iter::once(encoder.finish()));
+        }
+
+        let uniform = FrameUniform {
+            index: self.frame,
+            samples: self.samples_per_frame,
+        };
+        engine
+            .queue()
+            .write_buffer(&self.frame_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        let mut encoder = engine.new_encoder();
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Path Trace Compute Pass"),
+                timestamp_writes: None,
+            });
+
+            compute_pass.set_pipeline(&self.compute_pipeline);
+            compute_pass.set_bind_group(0, &self.textures.scene_bind_group, &[]);
+            compute_pass.set_bind_group(1, &self.textures.accumulator_bind_group, &[]);
+            compute_pass.set_bind_group(2, engine.dimensions_bind_group(), &[]);
+            compute_pass.set_bind_group(3, &self.frame_bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                engine.width().div_ceil(WORKGROUP_SIZE),
+                engine.height().div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        // Copied back rather than ping-ponged so the accumulator stays the
+        // same texture for views to bind
+        encoder.copy_texture_to_texture(
+            self.textures.accumulator_texture.as_image_copy(),
+            self.textures.previous_texture.as_image_copy(),
+            self.textures.accumulator_texture.size(),
+        );
+        engine.queue().submit(std::iter::once(encoder.finish()));
+
+        self.frame += 1;
+    }
+}
+
+/// The captured scene and the accumulator, recreated when the engine size
+/// changes.
+struct PathTracerTextures {
+    width: u32,
+    height: u32,
+    distance_texture: wgpu::Texture,
+    material_texture: wgpu::Texture,
+    previous_texture: wgpu::Texture,
+    accumulator_texture: wgpu::Texture,
+    scene_bind_group: BindGroup,
+    accumulator_bind_group: BindGroup,
+}
+
+impl PathTracerTextures {
+    fn new(
+        device: &Device,
+        scene_layout: &BindGroupLayout,
+        accumulator_layout: &BindGroupLayout,
+        width: u32,
+        height: u32,
+    ) -> PathTracerTextures {
+        let texture_desc = wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ACCUMULATOR_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+            label: Some("Accumulator Texture"),
+        };
+
+        let accumulator_texture = device.create_texture(&texture_desc);
+        let previous_texture = device.create_texture(&wgpu::TextureDescriptor {
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("Previous Accumulator Texture"),
+            ..texture_desc
+        });
+        let distance_texture = device.create_texture(&wgpu::TextureDescriptor {
+            format: engine::DISTANCE_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("Path Trace Distance Texture"),
+            ..texture_desc
+        });
+        let material_texture = device.create_texture(&wgpu::TextureDescriptor {
+            format: engine::MATERIAL_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("Path Trace Material Texture"),
+            ..texture_desc
+        });
+
+        let scene_bind_group =
+            create_scene_bind_group(device, scene_layout, &distance_texture, &material_texture);
+        let accumulator_bind_group = create_compute_bind_group(
+            device,
+            accumulator_layout,
+            &previous_texture.create_view(&Default::default()),
+            &accumulator_texture.create_view(&Default::default()),
+            "accumulator_bind_group",
+        );
+
+        Self {
+            width,
+            height,
+            distance_texture,
+            material_texture,
+            previous_texture,
+            accumulator_texture,
+            scene_bind_group,
+            accumulator_bind_group,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeds::Seed;
+
+    #[test]
+    fn converges_to_the_analytic_fluence() {
+        let (width, height) = (40, 30);
+        let (center, radius) = ([20., 15.], 3.);
+        let mut engine = pollster::block_on(Engine::headless(width, height, true)).unwrap();
+        engine
+            .add_seed(Seed::new(1, center, radius, [1., 0., 0., 1.]))
+            .unwrap();
+        engine.run().unwrap();
+
+        let mut path_tracer = PathTracer::new(&engine, 64);
+        for _ in 0..32 {
+            path_tracer.render(&mut engine);
+        }
+        assert_eq!(path_tracer.frame(), 32);
+        assert_eq!(path_tracer.sample_count(), 32 * 64);
+        let fluence = pollster::block_on(path_tracer.read(&engine)).unwrap();
+        let at = |x: u32, y: u32| fluence[(y * width + x) as usize];
+
+        // Every ray from inside the emitter hits it
+        assert_eq!(at(21, 15), [1., 0., 0.]);
+
+        // A disc of radius r at distance d covers 2 asin(r / d) of the
+        // directions, measured from the pixel centre. The pixels of the
+        // emitter stick out of the disc a little, so it looks larger.
+        for (x, y) in [(30, 15), (20, 5), (0, 0)] {
+            let distance = (x as f32 + 0.5 - center[0]).hypot(y as f32 + 0.5 - center[1]);
+            let exact = (radius / distance).asin() / std::f32::consts::PI;
+            let [r, g, b] = at(x, y);
+            assert!(
+                (r - exact).abs() < 0.15 * exact,
+                "({x}, {y}): {r} instead of {exact}"
+            );
+            assert_eq!([g, b], [0., 0.]);
+        }
+
+        path_tracer.reset();
+        assert_eq!(path_tracer.sample_count(), 0);
+        let fluence = pollster::block_on(path_tracer.read(&engine)).unwrap();
+        assert!(fluence.iter().all(|rgb| *rgb == [0.; 3]));
+    }
+}
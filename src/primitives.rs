@@ -0,0 +1,291 @@
+//! Vector shapes rasterized into the seed texture alongside the seed discs.
+
+use crate::seeds::is_valid_weight;
+use std::f32::consts::TAU;
+
+/// The geometry of a [`Primitive`], in pixels.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Shape {
+    Segment([f32; 2], [f32; 2]),
+    Polyline(Vec<[f32; 2]>),
+    /// Closed, the last point connects back to the first.
+    Polygon(Vec<[f32; 2]>),
+    Circle {
+        center: [f32; 2],
+        radius: f32,
+    },
+    /// Start, control and end point.
+    QuadraticBezier([[f32; 2]; 3]),
+    /// Start, two control points and end point.
+    CubicBezier([[f32; 2]; 4]),
+}
+
+/// How a [`Shape`] covers pixels.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Style {
+    /// Pixels within half the thickness of the outline, at least one pixel
+    /// wide.
+    Stroke { thickness: f32 },
+    /// Pixels inside the shape by the even-odd rule. Open shapes are closed
+    /// by a straight line.
+    Fill,
+}
+
+/// A shape whose covered pixels are all seeds at their own coordinate, so
+/// distances are measured to the shape itself rather than a centre.
+///
+/// Primitives are drawn over the seed discs, later ones over earlier ones.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Primitive {
+    pub id: u32,
+    pub shape: Shape,
+    pub style: Style,
+    /// Drawn into the material texture.
+    pub color: [f32; 4],
+    /// Used by [`crate::engine::EngineConfig::weighting`], 1 by default.
+    /// Must be positive, multiplicative weighting divides by it.
+    #[serde(default = "crate::seeds::default_weight")]
+    pub weight: f32,
+}
+
+impl Primitive {
+    pub fn new(id: u32, shape: Shape, style: Style, color: [f32; 4]) -> Primitive {
+        Primitive {
+            id,
+            shape,
+            style,
+            color,
+            weight: 1.,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// If the weight isn't a positive finite number.
+    pub fn with_weight(self, weight: f32) -> Primitive {
+        assert!(
+            is_valid_weight(weight),
+            "weights must be positive, not {weight}"
+        );
+        Primitive { weight, ..self }
+    }
+
+    /// The outline as straight segments between consecutive points, and
+    /// whether the last point connects back to the first.
+    pub fn outline(&self) -> (Vec<[f32; 2]>, bool) {
+        match &self.shape {
+            Shape::Segment(from, to) => (vec![*from, *to], false),
+            Shape::Polyline(points) => (points.clone(), false),
+            Shape::Polygon(points) => (points.clone(), true),
+            Shape::Circle { center, radius } => {
+                let count = segment_count(TAU * radius);
+                let points = (0..count)
+                    .map(|i| {
+                        let angle = TAU * i as f32 / count as f32;
+                        [
+                            center[0] + radius * angle.cos(),
+                            center[1] + radius * angle.sin(),
+                        ]
+                    })
+                    .collect();
+                (points, true)
+            }
+            Shape::QuadraticBezier([p0, p1, p2]) => {
+                let count = segment_count(length(*p0, *p1) + length(*p1, *p2));
+                let points = (0..=count)
+                    .map(|i| {
+                        let t = i as f32 / count as f32;
+                        let s = 1. - t;
+                        combine(&[(s * s, *p0), (2. * s * t, *p1), (t * t, *p2)])
+                    })
+                    .collect();
+                (points, false)
+            }
+            Shape::CubicBezier([p0, p1, p2, p3]) => {
+                let count = segment_count(length(*p0, *p1) + length(*p1, *p2) + length(*p2, *p3));
+                let points = (0..=count)
+                    .map(|i| {
+                        let t = i as f32 / count as f32;
+                        let s = 1. - t;
+                        combine(&[
+                            (s * s * s, *p0),
+                            (3. * s * s * t, *p1),
+                            (3. * s * t * t, *p2),
+                            (t * t * t, *p3),
+                        ])
+                    })
+                    .collect();
+                (points, false)
+            }
+        }
+    }
+}
+
+/// A primitive in the storage buffer, its outline points are in a separate
+/// buffer. Matches `struct Primitive` in `seeds.wgsl`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct GpuPrimitive {
+    first_point: u32,
+    point_count: u32,
+    kind: u32,
+    id: u32,
+    color: [f32; 4],
+    thickness: f32,
+    weight: f32,
+    _padding: [f32; 2],
+}
+
+// Matches the kinds in seeds.wgsl
+const KIND_OPEN_STROKE: u32 = 0;
+const KIND_CLOSED_STROKE: u32 = 1;
+const KIND_FILL: u32 = 2;
+
+impl GpuPrimitive {
+    /// Appends the outline of the primitive to `points`.
+    pub(crate) fn new(primitive: &Primitive, points: &mut Vec<[f32; 2]>) -> GpuPrimitive {
+        let (outline, closed) = primitive.outline();
+        let (kind, thickness) = match primitive.style {
+            Style::Stroke { thickness } if closed => (KIND_CLOSED_STROKE, thickness),
+            Style::Stroke { thickness } => (KIND_OPEN_STROKE, thickness),
+            Style::Fill => (KIND_FILL, 0.),
+        };
+
+        let first_point = points.len() as u32;
+        points.extend(&outline);
+
+        GpuPrimitive {
+            first_point,
+            point_count: outline.len() as u32,
+            kind,
+            id: primitive.id,
+            color: primitive.color,
+            thickness,
+            weight: primitive.weight,
+            _padding: [0.; 2],
+        }
+    }
+}
+
+/// Enough straight segments to stay within a fraction of a pixel of a curve
+/// of this length.
+fn segment_count(length: f32) -> u32 {
+    ((length / 2.).ceil() as u32).clamp(8, 1024)
+}
+
+fn length(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+fn combine(terms: &[(f32, [f32; 2])]) -> [f32; 2] {
+    terms.iter().fold([0., 0.], |sum, (factor, point)| {
+        [sum[0] + factor * point[0], sum[1] + factor * point[1]]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::readback::NearestSeed;
+
+    fn primitive(shape: Shape) -> Primitive {
+        Primitive::new(1, shape, Style::Fill, [1.; 4])
+    }
+
+    #[test]
+    fn straight_outlines() {
+        assert_eq!(
+            primitive(Shape::Segment([0., 0.], [4., 2.])).outline(),
+            (vec![[0., 0.], [4., 2.]], false)
+        );
+
+        let points = vec![[0., 0.], [4., 0.], [4., 4.]];
+        assert_eq!(
+            primitive(Shape::Polyline(points.clone())).outline(),
+            (points.clone(), false)
+        );
+        assert_eq!(
+            primitive(Shape::Polygon(points.clone())).outline(),
+            (points, true)
+        );
+    }
+
+    #[test]
+    fn circle_outline() {
+        let (points, closed) = primitive(Shape::Circle {
+            center: [10., 20.],
+            radius: 5.,
+        })
+        .outline();
+
+        assert!(closed);
+        assert!(points.len() >= 8);
+        for [x, y] in points {
+            assert!(((x - 10.).hypot(y - 20.) - 5.).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn bezier_outlines_end_at_their_end_points() {
+        let curves = [
+            Shape::QuadraticBezier([[0., 0.], [5., 10.], [10., 0.]]),
+            Shape::CubicBezier([[0., 0.], [0., 10.], [10., 10.], [10., 0.]]),
+        ];
+
+        for shape in curves {
+            let (points, closed) = primitive(shape).outline();
+            assert!(!closed);
+            assert!(points.len() > 2);
+            assert_eq!(points[0], [0., 0.]);
+            let [x, y] = points[points.len() - 1];
+            assert!((x - 10.).abs() < 1e-4 && y.abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn rasterized_pixels_are_their_own_seeds() {
+        let (width, height) = (24, 16);
+        let mut engine = pollster::block_on(Engine::headless(width, height, true)).unwrap();
+        let square = |from: f32, to: f32| {
+            Shape::Polygon(vec![[from, from], [to, from], [to, to], [from, to]])
+        };
+        engine.add_primitive(primitive(square(2., 8.))).unwrap();
+        engine
+            .add_primitive(Primitive::new(
+                2,
+                Shape::Segment([12.2, 4.5], [19.8, 4.5]),
+                Style::Stroke { thickness: 1. },
+                [1.; 4],
+            ))
+            .unwrap();
+        // Drawn over the first square
+        engine
+            .add_primitive(Primitive::new(3, square(6., 12.), Style::Fill, [1.; 4]))
+            .unwrap();
+        engine.run().unwrap();
+
+        // Pixels whose centre the shapes cover
+        let covering = |x: u32, y: u32| match (x, y) {
+            (6..=11, 6..=11) => Some(3),
+            (2..=7, 2..=7) => Some(1),
+            (12..=19, 4) => Some(2),
+            _ => None,
+        };
+        let nearest = pollster::block_on(engine.read_nearest()).unwrap();
+        for (i, nearest) in nearest.into_iter().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            let nearest = nearest.unwrap();
+            match covering(x, y) {
+                Some(id) => assert_eq!(
+                    nearest,
+                    NearestSeed {
+                        position: [x, y],
+                        id
+                    }
+                ),
+                None => assert_ne!(nearest.position, [x, y], "({x}, {y})"),
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+// Radiance probes laid out in square blocks of texels, one texel per ray
+// direction, row by row within the block
+
+// The constants of one cascade, matches `CascadeUniform` in cascades.rs
+struct Cascade {
+    index: u32,
+    // 1 for the top cascade, which has nothing above to merge
+    is_top: u32,
+    // Distance between probes in pixels
+    spacing: f32,
+    // Distances from the probe at which its rays start and end
+    interval_start: f32,
+    interval_end: f32,
+}
+
+@group(3) @binding(0)
+var<uniform> cascade: Cascade;
+
+// The number of probes in each direction. The probes sit on pixel centers
+// from the first to the last pixel, so every pixel lies between four of
+// them.
+fn probe_count(spacing: f32) -> vec2<u32> {
+    return vec2<u32>(ceil((dimensions - 1.) / spacing)) + 1u;
+}
+
+fn probe_position(probe: vec2<u32>, spacing: f32) -> vec2<f32> {
+    return vec2<f32>(probe) * spacing + 0.5;
+}
+
+// One of the four probes around a position, with its bilinear weight
+struct ProbeCorner {
+    probe: vec2<u32>,
+    weight: f32,
+}
+
+fn probe_corner(pos: vec2<f32>, spacing: f32, corner: u32) -> ProbeCorner {
+    let count = vec2<i32>(probe_count(spacing));
+    let probe_pos = (pos - 0.5) / spacing;
+    let offset = vec2<i32>(i32(corner & 1u), i32(corner >> 1u));
+    let probe = clamp(vec2<i32>(floor(probe_pos)) + offset, vec2<i32>(0), count - 1);
+    let weight = mix(1. - fract(probe_pos), fract(probe_pos), vec2<f32>(offset));
+    return ProbeCorner(vec2<u32>(probe), weight.x * weight.y);
+}
+
+// The radiance of four consecutive directions of a probe in `t_probes`,
+// averaged. `block` is the side of a probe's block of texels.
+fn probe_directions(probe: vec2<u32>, block: u32, first_direction: u32) -> vec4<f32> {
+    var sum = vec4<f32>(0.);
+    for (var i = 0u; i < 4u; i++) {
+        let direction = first_direction + i;
+        let texel = probe * block + vec2<u32>(direction % block, direction / block);
+        sum += textureLoad(t_probes, texel, 0);
+    }
+    return sum / 4.;
+}
@@ -0,0 +1,317 @@
+//! Sphere tracing through a distance field, on the GPU with raymarch.wgsl
+//! and on the CPU with a [`DistanceField`] read back from it.
+
+use crate::engine::{
+    self, create_render_pipeline, Engine, EngineConfig, Metric, Vertex, Weighting, NO_SEED_COLOR,
+};
+use wgpu::{self, BindGroup, BindGroupLayout, Device};
+
+/// Steps per ray, rays that run out of steps count as misses.
+pub const MAX_MARCH_STEPS: u32 = 64;
+
+// The distance field is measured between pixel centers, and a point in a
+// pixel is up to half a diagonal from its center, so the nearest surface
+// may be up to a diagonal closer
+const PIXEL_DIAGONAL: f32 = std::f32::consts::SQRT_2;
+
+/// Where a ray stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// Whether the ray entered a surface pixel, rather than leaving the
+    /// field, reaching its maximum distance or running out of steps.
+    pub hit: bool,
+    pub position: [f32; 2],
+    /// The sRGB colour of the surface pixel, transparent black on a miss.
+    pub material: [u8; 4],
+    /// How far the ray travelled from its origin.
+    pub distance: f32,
+}
+
+/// The distance in pixels from every pixel to the nearest surface pixel,
+/// where the distance is 0, and the colours of the surface pixels.
+#[derive(Debug, Clone)]
+pub struct DistanceField {
+    width: u32,
+    height: u32,
+    distances: Vec<f32>,
+    materials: Vec<[u8; 4]>,
+}
+
+impl DistanceField {
+    /// Row-major distances and materials of a field of the given size.
+    pub fn new(
+        width: u32,
+        height: u32,
+        distances: Vec<f32>,
+        materials: Vec<[u8; 4]>,
+    ) -> DistanceField {
+        let len = (width * height) as usize;
+        assert_eq!(distances.len(), len, "the distances don't match the size");
+        assert_eq!(materials.len(), len, "the materials don't match the size");
+
+        Self {
+            width,
+            height,
+            distances,
+            materials,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The distance at a pixel, `None` outside the field.
+    pub fn distance(&self, [x, y]: [f32; 2]) -> Option<f32> {
+        self.index([x, y]).map(|index| self.distances[index])
+    }
+
+    /// Marches from `origin` along the normalized `direction`, stepping by
+    /// the distance to the nearest surface.
+    pub fn march(&self, origin: [f32; 2], direction: [f32; 2], max_distance: f32) -> RayHit {
+        let at = |distance: f32| {
+            [
+                origin[0] + direction[0] * distance,
+                origin[1] + direction[1] * distance,
+            ]
+        };
+
+        let mut travelled = 0.;
+        for _ in 0..MAX_MARCH_STEPS {
+            if travelled > max_distance {
+                break;
+            }
+            let Some(index) = self.index(at(travelled)) else {
+                break;
+            };
+
+            let clearance = self.distances[index];
+            if clearance < 0.5 {
+                return RayHit {
+                    hit: true,
+                    position: at(travelled),
+                    material: self.materials[index],
+                    distance: travelled,
+                };
+            }
+            travelled += (clearance - PIXEL_DIAGONAL).max(0.5);
+        }
+
+        let distance = travelled.min(max_distance);
+        RayHit {
+            hit: false,
+            position: at(distance),
+            material: [0; 4],
+            distance,
+        }
+    }
+
+    fn index(&self, [x, y]: [f32; 2]) -> Option<usize> {
+        let inside = x >= 0. && y >= 0. && x < self.width as f32 && y < self.height as f32;
+        inside.then(|| (y as u32 * self.width + x as u32) as usize)
+    }
+}
+
+/// Fills the distance texture of an [`Engine`] with the distance to the
+/// surface pixels of its material texture: every pixel that is neither
+/// white nor transparent.
+///
+/// Unlike the seeds, whose distance is measured to their center, this
+/// gives the distance to their edge that rays need.
+pub struct SurfaceField {
+    seed_render_pipeline: wgpu::RenderPipeline,
+}
+
+impl SurfaceField {
+    pub fn new(engine: &Engine) -> SurfaceField {
+        let device = engine.device();
+
+        let seed_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Surface Seed Render Pipeline Layout"),
+                bind_group_layouts: &[engine.material_bind_group_layout()],
+                push_constant_ranges: &[],
+            });
+
+        let seed_render_pipeline = create_render_pipeline(
+            "Surface Seed Render Pipeline",
+            device,
+            &seed_render_pipeline_layout,
+            engine::JFA_TEXTURE_FORMAT,
+            &[Vertex::desc()],
+            shader!("surface_seed.wgsl", "no_seed.wgsl"),
+        );
+
+        Self {
+            seed_render_pipeline,
+        }
+    }
+
+    /// Runs a jump flood from the surface pixels of the material texture of
+    /// the last [`Engine::run`], overwriting the engine's nearest-seed and
+    /// distance outputs.
+    ///
+    /// The jump flood uses the engine's wrap and backend settings, but always
+    /// the unweighted Euclidean distance in pixels rays need to not step
+    /// through surfaces.
+    pub fn generate(&self, engine: &mut Engine) {
+        let config = engine.config().clone();
+        *engine.config_mut() = EngineConfig {
+            metric: Metric::Euclidean,
+            weighting: Weighting::None,
+            spread: None,
+            ..config.clone()
+        };
+        engine.write_options();
+
+        let mut encoder = engine.new_encoder();
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Surface Seed Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: engine.seed_texture_view(),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(NO_SEED_COLOR),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(&self.seed_render_pipeline);
+            render_pass.set_bind_group(0, engine.material_bind_group(), &[]);
+            render_pass.set_vertex_buffer(0, engine.vertex_buffer().slice(..));
+            render_pass.draw(0..3, 0..1);
+        }
+        engine.queue().submit(std::iter::once(encoder.finish()));
+
+        engine.jump_flood();
+        engine.write_distances();
+        *engine.config_mut() = config;
+    }
+}
+
+/// Layout of the distance and material textures raymarch.wgsl reads, for
+/// compute shaders.
+pub(crate) fn create_scene_bind_group_layout(device: &Device) -> BindGroupLayout {
+    let entry = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+        },
+        count: None,
+    };
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[entry(0), entry(1)],
+        label: Some("scene_bind_group_layout"),
+    })
+}
+
+pub(crate) fn create_scene_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    distance_texture: &wgpu::Texture,
+    material_texture: &wgpu::Texture,
+) -> BindGroup {
+    let distance_view = distance_texture.create_view(&Default::default());
+    let material_view = material_texture.create_view(&Default::default());
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&distance_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&material_view),
+            },
+        ],
+        label: Some("scene_bind_group"),
+    })
+}
+
+impl Engine {
+    /// The distance texture in pixels and the material texture, for rays
+    /// through the seeds after the last [`Engine::run`] or through the
+    /// surfaces after the last [`SurfaceField::generate`].
+    pub async fn read_distance_field(&self) -> anyhow::Result<DistanceField> {
+        let mut distances = self.read_distance_texture().await?;
+        // Saturated distances stay a lower bound
+        if let Some(spread) = self.config().spread.filter(|&spread| spread > 0.) {
+            distances
+                .iter_mut()
+                .for_each(|distance| *distance *= spread);
+        }
+        let materials = self.read_material().await?;
+
+        Ok(DistanceField::new(
+            self.width(),
+            self.height(),
+            distances,
+            materials,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+
+    // A row of 16 pixels whose only surface is pixel 10
+    fn row() -> DistanceField {
+        let distances = (0..16).map(|x| (x as f32 - 10.).abs()).collect();
+        let materials = (0..16)
+            .map(|x| if x == 10 { RED } else { [0; 4] })
+            .collect();
+        DistanceField::new(16, 1, distances, materials)
+    }
+
+    #[test]
+    fn march_hits_the_surface() {
+        let hit = row().march([0.5, 0.5], [1., 0.], 100.);
+
+        assert!(hit.hit);
+        assert_eq!(hit.material, RED);
+        assert!((10. ..11.).contains(&hit.position[0]));
+        assert!((hit.distance - (hit.position[0] - 0.5)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn march_misses() {
+        let field = row();
+
+        // Leaves the field
+        let away = field.march([5.5, 0.5], [-1., 0.], 100.);
+        assert!(!away.hit);
+        assert_eq!(away.material, [0; 4]);
+
+        // Stops before the surface
+        let short = field.march([0.5, 0.5], [1., 0.], 3.);
+        assert!(!short.hit);
+        assert_eq!(short.distance, 3.);
+        assert_eq!(short.position, [3.5, 0.5]);
+    }
+
+    #[test]
+    fn distance_outside_the_field() {
+        let field = row();
+        assert_eq!(field.distance([10.5, 0.5]), Some(0.));
+        assert_eq!(field.distance([-0.5, 0.5]), None);
+        assert_eq!(field.distance([16., 0.5]), None);
+    }
+}
@@ -0,0 +1,50 @@
+// Sphere tracing through a distance field. Expects the including shader to
+// bind `t_distance`, the distance in pixels from every pixel to the nearest
+// surface pixel, `t_material`, the colours of the surface pixels, and
+// `dimensions`. Matches `DistanceField::march` in raymarch.rs.
+
+// Steps per ray, rays that run out of steps count as misses
+const MAX_MARCH_STEPS = 64u;
+
+// The distance field is measured between pixel centers, and a point in a
+// pixel is up to half a diagonal from its center, so the nearest surface
+// may be up to a diagonal closer
+const PIXEL_DIAGONAL = 1.4142135;
+
+struct RayHit {
+    // Whether the ray entered a surface pixel, rather than leaving the
+    // texture, reaching its maximum distance or running out of steps
+    hit: bool,
+    // Where the ray stopped
+    position: vec2<f32>,
+    // The colour of the surface pixel, transparent black on a miss
+    material: vec4<f32>,
+    // How far the ray travelled from its origin
+    distance: f32,
+}
+
+// Marches from `origin` along the normalized `direction`, stepping by the
+// distance to the nearest surface.
+fn ray_march(origin: vec2<f32>, direction: vec2<f32>, max_distance: f32) -> RayHit {
+    var result = RayHit(false, origin, vec4<f32>(0.), 0.);
+    for (var i = 0u; i < MAX_MARCH_STEPS && result.distance <= max_distance; i++) {
+        result.position = origin + direction * result.distance;
+        if any(result.position < vec2<f32>(0.)) || any(result.position >= dimensions) {
+            break;
+        }
+
+        let pixel = vec2<u32>(result.position);
+        let clearance = textureLoad(t_distance, pixel, 0).r;
+        if clearance < 0.5 {
+            result.hit = true;
+            result.material = textureLoad(t_material, pixel, 0);
+            break;
+        }
+        result.distance += max(clearance - PIXEL_DIAGONAL, 0.5);
+    }
+    if !result.hit {
+        result.distance = min(result.distance, max_distance);
+        result.position = origin + direction * result.distance;
+    }
+    return result;
+}
@@ -0,0 +1,255 @@
+//! Copies GPU results back into Rust vectors.
+
+use crate::cascades::RadianceCascades;
+use crate::engine::{Engine, NO_SEED};
+use crate::path_tracer::PathTracer;
+use crate::sdf::{SdfFormat, SignedDistanceField};
+use anyhow::Context;
+use wgpu::{Device, Queue};
+
+/// The nearest seed of a pixel, as stored in the ping-pong textures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NearestSeed {
+    /// The pixel the seed lies in.
+    pub position: [u32; 2],
+    /// The [`crate::seeds::Seed::id`], i.e. the region label of the pixel.
+    pub id: u32,
+}
+
+impl Engine {
+    /// The nearest seed of every pixel after the last [`Engine::run`],
+    /// row-major. `None` where no seed was found.
+    pub async fn read_nearest(&self) -> anyhow::Result<Vec<Option<NearestSeed>>> {
+        let bytes = read_texture(self.device(), self.queue(), self.output_texture()).await?;
+
+        Ok(bytes
+            .chunks_exact(16)
+            .map(|texel| {
+                let [x, y, id] = [0, 4, 8].map(|offset| {
+                    u32::from_le_bytes(texel[offset..offset + 4].try_into().unwrap())
+                });
+                (x != NO_SEED).then_some(NearestSeed {
+                    position: [x, y],
+                    id,
+                })
+            })
+            .collect())
+    }
+
+    /// Only the positions of [`Engine::read_nearest`].
+    pub async fn read_nearest_seeds(&self) -> anyhow::Result<Vec<Option<[u32; 2]>>> {
+        let nearest = self.read_nearest().await?;
+        Ok(nearest
+            .iter()
+            .map(|seed| seed.map(|seed| seed.position))
+            .collect())
+    }
+
+    /// The region label (seed id) of every pixel after the last
+    /// [`Engine::run`], row-major. `None` where no seed was found.
+    pub async fn read_labels(&self) -> anyhow::Result<Vec<Option<u32>>> {
+        let nearest = self.read_nearest().await?;
+        Ok(nearest
+            .iter()
+            .map(|seed| seed.map(|seed| seed.id))
+            .collect())
+    }
+
+    /// The material colour of every pixel's nearest seed after the last
+    /// [`Engine::run`] as sRGB bytes, row-major. Transparent black where no
+    /// seed was found.
+    pub async fn read_colors(&self) -> anyhow::Result<Vec<[u8; 4]>> {
+        let nearest = self.read_nearest_seeds().await?;
+        let material = self.read_material().await?;
+
+        Ok(nearest
+            .iter()
+            .map(|seed| match seed {
+                Some([x, y]) => material[(y * self.width() + x) as usize],
+                None => [0; 4],
+            })
+            .collect())
+    }
+
+    /// The contents of [`Engine::material_texture`] as sRGB bytes, row-major.
+    pub async fn read_material(&self) -> anyhow::Result<Vec<[u8; 4]>> {
+        let bytes = read_texture(self.device(), self.queue(), self.material_texture()).await?;
+        Ok(bytemuck::pod_collect_to_vec(&bytes))
+    }
+
+    /// The distance from every pixel to its nearest seed after the last
+    /// [`Engine::run`] in the configured metric, row-major. `f32::INFINITY`
+    /// where no seed was found.
+    pub async fn read_distances(&self) -> anyhow::Result<Vec<f32>> {
+        let (width, height) = (self.width(), self.height());
+        let seeds = self.read_nearest_seeds().await?;
+
+        Ok(seeds
+            .iter()
+            .enumerate()
+            .map(|(i, seed)| match seed {
+                Some([x, y]) => {
+                    let pixel = [(i as u32 % width) as f32, (i as u32 / width) as f32];
+                    self.config()
+                        .distance(pixel, [*x as f32, *y as f32], width, height)
+                }
+                None => f32::INFINITY,
+            })
+            .collect())
+    }
+
+    /// The contents of [`Engine::distance_texture`], row-major.
+    pub async fn read_distance_texture(&self) -> anyhow::Result<Vec<f32>> {
+        let bytes = read_texture(self.device(), self.queue(), self.distance_texture()).await?;
+        Ok(bytemuck::pod_collect_to_vec(&bytes))
+    }
+}
+
+impl SignedDistanceField {
+    /// The signed distance of every pixel after the last
+    /// [`SignedDistanceField::generate`], row-major.
+    pub async fn read(&self, engine: &Engine) -> anyhow::Result<Vec<f32>> {
+        let bytes = read_texture(engine.device(), engine.queue(), self.texture()).await?;
+
+        Ok(match self.format() {
+            SdfFormat::R32Float => bytemuck::pod_collect_to_vec(&bytes),
+            SdfFormat::R16Float => bytes
+                .chunks_exact(2)
+                .map(|texel| half::f16::from_le_bytes([texel[0], texel[1]]).to_f32())
+                .collect(),
+        })
+    }
+}
+
+impl RadianceCascades {
+    /// The fluence of every pixel after the last
+    /// [`RadianceCascades::render`] as linear RGBA, row-major.
+    pub async fn read(&self, engine: &Engine) -> anyhow::Result<Vec<[f32; 4]>> {
+        let bytes = read_texture(engine.device(), engine.queue(), self.fluence_texture()).await?;
+
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|texel| {
+                let mut rgba = [0.; 4];
+                for (channel, value) in texel.chunks_exact(2).zip(&mut rgba) {
+                    *value = half::f16::from_le_bytes([channel[0], channel[1]]).to_f32();
+                }
+                rgba
+            })
+            .collect())
+    }
+}
+
+impl PathTracer {
+    /// The mean radiance of the rays through every pixel since the last
+    /// [`PathTracer::reset`] as linear RGB, row-major. Zero before the first
+    /// [`PathTracer::render`].
+    pub async fn read(&self, engine: &Engine) -> anyhow::Result<Vec<[f32; 3]>> {
+        if self.frame() == 0 {
+            return Ok(vec![[0.; 3]; (engine.width() * engine.height()) as usize]);
+        }
+        let bytes =
+            read_texture(engine.device(), engine.queue(), self.accumulator_texture()).await?;
+
+        Ok(bytes
+            .chunks_exact(16)
+            .map(|texel| {
+                let [r, g, b, count] = [0, 4, 8, 12].map(|offset| {
+                    f32::from_le_bytes(texel[offset..offset + 4].try_into().unwrap())
+                });
+                [r, g, b].map(|sum| sum / count)
+            })
+            .collect())
+    }
+}
+
+/// Reads a whole texture into tightly packed rows, removing the padding
+/// `copy_texture_to_buffer` needs at the end of every row.
+pub(crate) async fn read_texture(
+    device: &Device,
+    queue: &Queue,
+    texture: &wgpu::Texture,
+) -> anyhow::Result<Vec<u8>> {
+    let texel_size = texture
+        .format()
+        .block_copy_size(None)
+        .context("texture format can't be copied")?;
+    let unpadded_bytes_per_row = texture.width() * texel_size;
+    let padded_bytes_per_row =
+        unpadded_bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row * texture.height()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(texture.height()),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = futures_intrusive::channel::shared::oneshot_channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .receive()
+        .await
+        .context("readback buffer was dropped")?
+        .context("failed to map readback buffer")?;
+
+    let mut bytes = Vec::with_capacity((unpadded_bytes_per_row * texture.height()) as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks(padded_bytes_per_row as usize) {
+            bytes.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    buffer.unmap();
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::JfaBackend;
+    use pollster::FutureExt;
+
+    #[test]
+    fn unseeded_pixels_read_back_as_no_seed() {
+        let mut engine = Engine::headless(8, 4, true).block_on().unwrap();
+
+        for backend in [JfaBackend::Fragment, JfaBackend::Compute] {
+            engine.config_mut().backend = backend;
+            engine.run().unwrap();
+
+            let bytes = read_texture(engine.device(), engine.queue(), engine.output_texture())
+                .block_on()
+                .unwrap();
+            let texels: Vec<[u32; 4]> = bytemuck::pod_collect_to_vec(&bytes);
+            assert_eq!(texels.len(), 32);
+            for texel in texels {
+                assert_eq!([texel[0], texel[1]], [NO_SEED; 2], "{backend:?}");
+            }
+            let nearest = engine.read_nearest().block_on().unwrap();
+            assert!(nearest.iter().all(Option::is_none), "{backend:?}");
+        }
+    }
+}
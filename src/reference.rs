@@ -0,0 +1,310 @@
+//! Exact CPU versions of the jump flood results, to validate the GPU output.
+
+use crate::engine::EngineConfig;
+
+/// Relative difference below which two distances are considered equal, so
+/// rounding differences between the CPU and the shaders don't count as
+/// misclassified pixels.
+const TOLERANCE: f32 = 1e-4;
+
+/// Brute-force nearest-seed map and distance transform.
+pub struct ReferenceField {
+    pub width: u32,
+    pub height: u32,
+    /// Nearest seed of every pixel, row-major. `None` if there are no seeds.
+    pub nearest: Vec<Option<[u32; 2]>>,
+    /// Unweighted distance from every pixel to its nearest seed, like the
+    /// engine's distance outputs. `f32::INFINITY` if there are no seeds.
+    pub distance: Vec<f32>,
+    /// Weighted distance from every pixel to its nearest seed.
+    weighted_distance: Vec<f32>,
+    seeds: Vec<([u32; 2], f32)>,
+    config: EngineConfig,
+}
+
+/// How far a GPU result is from the [`ReferenceField`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    /// Pixels whose seed is farther away than the nearest one, or that have
+    /// a seed where there are none or none where there are. Ties between
+    /// equally distant seeds are not counted.
+    pub misclassified: usize,
+    /// Largest difference between the weighted distance to the GPU seed and
+    /// the exact one, `f32::INFINITY` if a pixel has a seed on only one side
+    /// or one that isn't in the reference.
+    pub max_distance_error: f32,
+}
+
+impl ReferenceField {
+    /// Checks every seed for every pixel, so only use this on small inputs.
+    ///
+    /// Distances are measured with the metric and wrap mode of the config,
+    /// like the engine does. All seeds have a weight of 1.
+    pub fn new(
+        seeds: &[[u32; 2]],
+        width: u32,
+        height: u32,
+        config: &EngineConfig,
+    ) -> ReferenceField {
+        let seeds: Vec<_> = seeds.iter().map(|seed| (*seed, 1.)).collect();
+        Self::weighted(&seeds, width, height, config)
+    }
+
+    /// Like [`ReferenceField::new`] for seeds with a weight each, compared
+    /// with the weighting of the config.
+    pub fn weighted(
+        seeds: &[([u32; 2], f32)],
+        width: u32,
+        height: u32,
+        config: &EngineConfig,
+    ) -> ReferenceField {
+        let mut field = Self {
+            width,
+            height,
+            nearest: Vec::with_capacity((width * height) as usize),
+            distance: Vec::with_capacity((width * height) as usize),
+            weighted_distance: Vec::with_capacity((width * height) as usize),
+            seeds: seeds.to_vec(),
+            config: config.clone(),
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let closest = seeds
+                    .iter()
+                    .map(|(seed, weight)| (*seed, field.weigh([x, y], *seed, *weight)))
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+
+                field.nearest.push(closest.map(|(seed, _)| seed));
+                field
+                    .distance
+                    .push(closest.map_or(f32::INFINITY, |(seed, _)| field.measure([x, y], seed)));
+                field
+                    .weighted_distance
+                    .push(closest.map_or(f32::INFINITY, |(_, distance)| distance));
+            }
+        }
+
+        field
+    }
+
+    /// Compares a row-major nearest-seed map of the same size, e.g. read back
+    /// from the engine.
+    pub fn compare(&self, nearest: &[Option<[u32; 2]>]) -> Comparison {
+        assert_eq!(
+            nearest.len(),
+            self.nearest.len(),
+            "the nearest-seed map doesn't match the reference size"
+        );
+
+        let mut comparison = Comparison {
+            misclassified: 0,
+            max_distance_error: 0.,
+        };
+
+        for (i, (seed, reference)) in nearest.iter().zip(&self.nearest).enumerate() {
+            let pixel = [i as u32 % self.width, i as u32 / self.width];
+
+            // A seed the reference doesn't know is as wrong as a missing one
+            let weight = seed.and_then(|seed| {
+                self.seeds
+                    .iter()
+                    .find(|(position, _)| *position == seed)
+                    .map(|(_, weight)| *weight)
+            });
+
+            let error = match (seed, weight, reference) {
+                (None, _, None) => 0.,
+                (Some(seed), Some(weight), Some(_)) => {
+                    let exact = self.weighted_distance[i];
+                    let error = self.weigh(pixel, *seed, weight) - exact;
+                    if error > TOLERANCE * exact.abs().max(1.) {
+                        comparison.misclassified += 1;
+                    }
+                    error
+                }
+                _ => {
+                    comparison.misclassified += 1;
+                    f32::INFINITY
+                }
+            };
+
+            comparison.max_distance_error = comparison.max_distance_error.max(error.abs());
+        }
+
+        comparison
+    }
+
+    fn measure(&self, pixel: [u32; 2], seed: [u32; 2]) -> f32 {
+        self.config.distance(
+            pixel.map(|x| x as f32),
+            seed.map(|x| x as f32),
+            self.width,
+            self.height,
+        )
+    }
+
+    fn weigh(&self, pixel: [u32; 2], seed: [u32; 2], weight: f32) -> f32 {
+        self.config
+            .weighting
+            .apply(self.measure(pixel, seed), weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Engine, JfaBackend, JfaVariant, Metric, Weighting};
+    use crate::seeds::Seed;
+
+    const SEEDS: [[u32; 2]; 5] = [[3, 4], [40, 9], [17, 30], [60, 45], [29, 18]];
+
+    #[test]
+    fn finds_the_nearest_seed() {
+        let reference = ReferenceField::new(&[[1, 1], [6, 1]], 8, 2, &EngineConfig::default());
+
+        assert_eq!(reference.nearest[0], Some([1, 1]));
+        assert_eq!(reference.nearest[7], Some([6, 1]));
+        assert_eq!(reference.distance[0], 2f32.sqrt());
+        assert_eq!(reference.distance[8 + 4], 2.);
+    }
+
+    #[test]
+    fn measures_with_the_config() {
+        let config = EngineConfig {
+            metric: Metric::Manhattan,
+            wrap: true,
+            ..Default::default()
+        };
+        let reference = ReferenceField::new(&[[0, 0]], 8, 8, &config);
+
+        // One pixel away in both directions across the corner
+        assert_eq!(reference.distance[8 * 8 - 1], 2.);
+        assert_eq!(reference.distance[8 * 4 + 4], 8.);
+    }
+
+    #[test]
+    fn no_seeds_are_infinitely_far() {
+        let reference = ReferenceField::new(&[], 4, 4, &EngineConfig::default());
+
+        assert!(reference.nearest.iter().all(Option::is_none));
+        assert!(reference.distance.iter().all(|d| *d == f32::INFINITY));
+        assert_eq!(reference.compare(&[None; 16]).misclassified, 0);
+        assert_eq!(reference.compare(&[Some([0, 0]); 16]).misclassified, 16);
+    }
+
+    #[test]
+    fn weights_move_the_boundary() {
+        let config = EngineConfig {
+            weighting: Weighting::Multiplicative,
+            ..Default::default()
+        };
+        let reference = ReferenceField::weighted(&[([0, 0], 1.), ([8, 0], 2.)], 9, 1, &config);
+
+        assert_eq!(reference.nearest[2], Some([0, 0]));
+        assert_eq!(reference.nearest[3], Some([8, 0]));
+        assert_eq!(reference.distance[3], 5.);
+
+        let unweighted = reference.compare(&[0, 0, 0, 0, 8, 8, 8, 8, 8].map(|x| Some([x, 0])));
+        assert_eq!(unweighted.misclassified, 1);
+    }
+
+    #[test]
+    fn compare_counts_farther_seeds() {
+        let reference = ReferenceField::new(&[[0, 0], [4, 0]], 5, 1, &EngineConfig::default());
+
+        let exact = reference.compare(&reference.nearest);
+        assert_eq!(exact.misclassified, 0);
+        assert_eq!(exact.max_distance_error, 0.);
+
+        // The middle pixel is as far from both seeds
+        let tied = [0, 0, 4, 4, 4].map(|x| Some([x, 0]));
+        assert_eq!(reference.compare(&tied).misclassified, 0);
+
+        let wrong =
+            reference.compare(&[Some([0, 0]), Some([0, 0]), Some([0, 0]), Some([0, 0]), None]);
+        assert_eq!(wrong.misclassified, 2);
+        assert_eq!(wrong.max_distance_error, f32::INFINITY);
+    }
+
+    #[test]
+    fn both_backends_match_the_reference() {
+        let (width, height) = (64, 48);
+        let mut engine = pollster::block_on(Engine::headless(width, height, true)).unwrap();
+        for (id, [x, y]) in SEEDS.iter().enumerate() {
+            let center = [*x as f32 + 0.5, *y as f32 + 0.5];
+            engine
+                .add_seed(Seed::new(id as u32, center, 0.5, [1.; 4]))
+                .unwrap();
+        }
+        // Plain JFA misclassifies a few of these pixels
+        engine.config_mut().variant = JfaVariant::JfaPlusTwo;
+
+        for metric in [
+            Metric::Euclidean,
+            Metric::Manhattan,
+            Metric::Chebyshev,
+            Metric::Minkowski(3.),
+        ] {
+            for wrap in [false, true] {
+                let reference = ReferenceField::new(
+                    &SEEDS,
+                    width,
+                    height,
+                    &EngineConfig {
+                        metric,
+                        wrap,
+                        ..Default::default()
+                    },
+                );
+
+                for backend in [JfaBackend::Fragment, JfaBackend::Compute] {
+                    let config = engine.config_mut();
+                    config.metric = metric;
+                    config.wrap = wrap;
+                    config.backend = backend;
+                    engine.run().unwrap();
+
+                    let nearest = pollster::block_on(engine.read_nearest_seeds()).unwrap();
+                    let comparison = reference.compare(&nearest);
+                    assert_eq!(
+                        comparison.misclassified, 0,
+                        "{metric:?}, wrap {wrap}, {backend:?}"
+                    );
+                    assert!(comparison.max_distance_error < 1e-3);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_seeds_match_the_reference() {
+        let (width, height) = (64, 48);
+        let seeds: Vec<_> = SEEDS.into_iter().zip([1.5, 0.5, 2., 1., 3.]).collect();
+        let mut engine = pollster::block_on(Engine::headless(width, height, true)).unwrap();
+        for (id, ([x, y], weight)) in seeds.iter().enumerate() {
+            let center = [*x as f32 + 0.5, *y as f32 + 0.5];
+            engine
+                .add_seed(Seed::new(id as u32, center, 0.5, [1.; 4]).with_weight(*weight))
+                .unwrap();
+        }
+        engine.config_mut().variant = JfaVariant::JfaPlusTwo;
+
+        for weighting in [
+            Weighting::Additive,
+            Weighting::Multiplicative,
+            Weighting::Power,
+        ] {
+            engine.config_mut().weighting = weighting;
+            engine.run().unwrap();
+
+            let reference = ReferenceField::weighted(&seeds, width, height, engine.config());
+            let nearest = pollster::block_on(engine.read_nearest_seeds()).unwrap();
+            assert_eq!(
+                reference.compare(&nearest).misclassified,
+                0,
+                "{weighting:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Scenes described in RON files: the canvas size, the seeds and primitives,
+//! the images and the settings of the passes.
+
+use crate::engine::{Engine, EngineConfig};
+use crate::image_seeds::{SeedImage, SeedPredicate};
+use crate::primitives::Primitive;
+use crate::seeds::{check_weight, Seed};
+use anyhow::{bail, Context};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Everything an [`Engine`] draws, in a form that can be stored on disk.
+///
+/// Fields missing from a file take their default values, so a scene can be
+/// as short as `(width: 512, height: 512, seeds: [...])`.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub width: u32,
+    pub height: u32,
+    pub seeds: Vec<Seed>,
+    pub primitives: Vec<Primitive>,
+    /// Seeds the jump flood from an image instead of the seeds and primitives.
+    pub seed_image: Option<SeedImageFile>,
+    /// Used as the material texture instead of the seed colours.
+    pub material_image: Option<PathBuf>,
+    pub config: EngineConfig,
+    /// Relative image paths are relative to this directory, the one of the
+    /// file the scene was opened from.
+    #[serde(skip)]
+    directory: PathBuf,
+}
+
+/// An image file whose pixels become seeds, see [`SeedImage`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SeedImageFile {
+    pub path: PathBuf,
+    pub predicate: SeedPredicate,
+}
+
+impl Scene {
+    pub fn new(width: u32, height: u32) -> Scene {
+        Scene {
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    /// Loads a scene from a RON file. Relative image paths are relative to
+    /// the directory of the file.
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Scene> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read scene {}", path.display()))?;
+        let mut scene: Scene = ron::from_str(&text)
+            .with_context(|| format!("failed to parse scene {}", path.display()))?;
+        scene.directory = directory(path).to_path_buf();
+
+        Ok(scene)
+    }
+
+    /// Writes the scene to a RON file. Image paths are kept as they are,
+    /// unless the file is in another directory than the one the scene was
+    /// opened from, which they are then resolved against.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let config = ron::ser::PrettyConfig::default();
+        let text = if directory(path) == self.directory {
+            ron::ser::to_string_pretty(self, config)?
+        } else {
+            let mut scene = self.clone();
+            if let Some(seed_image) = &mut scene.seed_image {
+                seed_image.path = std::path::absolute(self.resolve(&seed_image.path))?;
+            }
+            if let Some(material_image) = &mut scene.material_image {
+                *material_image = std::path::absolute(self.resolve(material_image))?;
+            }
+            ron::ser::to_string_pretty(&scene, config)?
+        };
+        std::fs::write(path, text)
+            .with_context(|| format!("failed to write scene {}", path.display()))
+    }
+
+    /// An image path relative to the directory the scene was opened from.
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.directory.join(path)
+    }
+
+    /// Replaces the seeds, primitives, images and settings of the engine and
+    /// resizes it to the canvas size.
+    pub fn apply(&self, engine: &mut Engine) -> anyhow::Result<()> {
+        self.validate()?;
+        let seed_image = self
+            .seed_image
+            .as_ref()
+            .map(|file| SeedImage::open(self.resolve(&file.path), file.predicate))
+            .transpose()?;
+        let material_image = self
+            .material_image
+            .as_ref()
+            .map(|path| {
+                let path = self.resolve(path);
+                image::open(&path)
+                    .map(|image| image.to_rgba8())
+                    .with_context(|| format!("failed to load material image {}", path.display()))
+            })
+            .transpose()?;
+
+        engine.resize(self.width, self.height);
+        engine.clear_seeds();
+        // Validated above, so this doesn't stop halfway
+        for seed in &self.seeds {
+            engine.add_seed(*seed)?;
+        }
+        for primitive in &self.primitives {
+            engine.add_primitive(primitive.clone())?;
+        }
+
+        engine.set_seed_image(seed_image);
+        engine.set_material_image(material_image);
+        *engine.config_mut() = self.config.clone();
+
+        Ok(())
+    }
+
+    /// Checks what the engine can't draw, before [`Scene::apply`] changes it.
+    fn validate(&self) -> anyhow::Result<()> {
+        for seed in &self.seeds {
+            check_weight("seed", seed.id, seed.weight)?;
+        }
+        for primitive in &self.primitives {
+            check_weight("primitive", primitive.id, primitive.weight)?;
+        }
+
+        // Seeds and primitives share the label channel
+        let mut ids = HashSet::new();
+        let seed_ids = self.seeds.iter().map(|seed| seed.id);
+        let primitive_ids = self.primitives.iter().map(|primitive| primitive.id);
+        if let Some(id) = seed_ids.chain(primitive_ids).find(|id| !ids.insert(*id)) {
+            bail!("more than one seed or primitive has the id {id}");
+        }
+        self.config.validate()
+    }
+
+    /// Takes the canvas size, seeds, primitives and settings from the
+    /// engine. The image files are kept, the engine doesn't know where its
+    /// images came from.
+    pub fn capture(&mut self, engine: &Engine) {
+        self.width = engine.width();
+        self.height = engine.height();
+        self.seeds = engine.seeds().to_vec();
+        self.primitives = engine.primitives().to_vec();
+        self.config = engine.config().clone();
+    }
+}
+
+/// The directory of a scene file, which relative paths in it start from.
+fn directory(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{JfaVariant, Metric, Weighting};
+    use crate::primitives::{Shape, Style};
+
+    fn scene() -> Scene {
+        let mut scene = Scene::new(64, 32);
+        scene.seeds = vec![
+            Seed::new(1, [3., 4.], 2., [1., 0., 0., 1.]),
+            Seed::new(2, [30., 20.], 5., [0., 1., 0., 1.]).with_weight(2.5),
+        ];
+        scene.primitives = vec![Primitive::new(
+            3,
+            Shape::Segment([0., 0.], [10., 10.]),
+            Style::Stroke { thickness: 2. },
+            [0., 0., 1., 1.],
+        )];
+        scene.seed_image = Some(SeedImageFile {
+            path: "seeds.png".into(),
+            predicate: SeedPredicate::AlphaAbove(128),
+        });
+        scene.material_image = Some("material.png".into());
+        scene.config = EngineConfig {
+            variant: JfaVariant::Custom {
+                pre: vec![1],
+                post: vec![2, 1],
+            },
+            spread: Some(8.),
+            wrap: true,
+            metric: Metric::Minkowski(3.),
+            weighting: Weighting::Power,
+            ..Default::default()
+        };
+        scene
+    }
+
+    fn to_ron(scene: &Scene) -> String {
+        ron::ser::to_string_pretty(scene, ron::ser::PrettyConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let text = to_ron(&scene());
+        let parsed: Scene = ron::from_str(&text).unwrap();
+
+        assert_eq!(parsed.seeds, scene().seeds);
+        assert_eq!(parsed.primitives, scene().primitives);
+        assert_eq!(parsed.config.metric, Metric::Minkowski(3.));
+        assert_eq!(to_ron(&parsed), text);
+    }
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let text = "(
+            width: 8,
+            height: 4,
+            seeds: [(position: (1, 2), radius: 1, id: 3, color: (1, 1, 1, 1))],
+        )";
+        let scene: Scene = ron::from_str(text).unwrap();
+
+        assert_eq!((scene.width, scene.height), (8, 4));
+        assert_eq!(scene.seeds[0].weight, 1.);
+        assert!(scene.primitives.is_empty());
+        assert_eq!(scene.config.metric, Metric::Euclidean);
+    }
+
+    #[test]
+    fn image_paths_survive_open_and_save() {
+        let directory = std::env::temp_dir().join(format!("scene-paths-{}", std::process::id()));
+        let other = directory.join("other");
+        std::fs::create_dir_all(&other).unwrap();
+        let path = directory.join("scene.ron");
+        std::fs::write(&path, to_ron(&scene())).unwrap();
+
+        let opened = Scene::open(&path).unwrap();
+        assert_eq!(
+            opened.material_image.as_deref(),
+            Some(Path::new("material.png"))
+        );
+        assert_eq!(
+            opened.resolve(Path::new("material.png")),
+            directory.join("material.png")
+        );
+
+        opened.save(&path).unwrap();
+        let reopened = Scene::open(&path).unwrap();
+        assert_eq!(reopened.material_image, opened.material_image);
+        assert_eq!(reopened.seed_image.unwrap().path, Path::new("seeds.png"));
+
+        // Saved elsewhere, the paths still point to the same files
+        opened.save(other.join("scene.ron")).unwrap();
+        let moved = Scene::open(other.join("scene.ron")).unwrap();
+        assert_eq!(
+            moved.resolve(moved.material_image.as_deref().unwrap()),
+            std::path::absolute(directory.join("material.png")).unwrap()
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn invalid_scenes_are_rejected() {
+        assert!(scene().validate().is_ok());
+
+        let mut duplicate = scene();
+        duplicate.seeds[1].id = 1;
+        assert!(duplicate.validate().is_err());
+
+        let mut shared = scene();
+        shared.primitives[0].id = 2;
+        assert!(shared.validate().is_err());
+
+        let mut weightless = scene();
+        weightless.primitives[0].weight = 0.;
+        assert!(weightless.validate().is_err());
+
+        let mut metric = scene();
+        metric.config.metric = Metric::Minkowski(0.);
+        assert!(metric.validate().is_err());
+    }
+}
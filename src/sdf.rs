@@ -0,0 +1,452 @@
+//! Signed distance fields of a shape given as an inside/outside mask.
+
+use crate::engine::{
+    self, create_render_pipeline, create_texture_bind_group, Engine, Vertex, NO_SEED_COLOR,
+};
+use anyhow::bail;
+use wgpu::{self, util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device};
+
+/// Format of the signed distance texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SdfFormat {
+    /// Can't be rendered to on downlevel backends like GLES and WebGL2.
+    #[default]
+    R32Float,
+    /// Half the size and renderable everywhere, within half a pixel for
+    /// distances up to 2048 pixels.
+    R16Float,
+}
+
+impl SdfFormat {
+    pub fn texture_format(self) -> wgpu::TextureFormat {
+        match self {
+            SdfFormat::R32Float => wgpu::TextureFormat::R32Float,
+            SdfFormat::R16Float => wgpu::TextureFormat::R16Float,
+        }
+    }
+
+    /// Whether the adapter can render into this format, which
+    /// [`SignedDistanceField::new`] falls back to R16Float without.
+    pub fn is_supported(self, adapter: &wgpu::Adapter) -> bool {
+        adapter
+            .get_texture_format_features(self.texture_format())
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+    }
+}
+
+/// Runs the jump flood of an [`Engine`] once from the boundary of the inside
+/// and once from the boundary of the outside of a mask, and combines both
+/// into a texture of signed distances in pixels: negative inside, positive
+/// outside, zero on the edge between two pixels.
+///
+/// Reuses the ping-pong textures of the engine, so its nearest-seed output
+/// is overwritten by [`SignedDistanceField::generate`].
+pub struct SignedDistanceField {
+    format: SdfFormat,
+    side_buffer: Buffer,
+    empty_bind_group: BindGroup,
+    mask_bind_group_layout: BindGroupLayout,
+    sdf_bind_group_layout: BindGroupLayout,
+    textures: SdfTextures,
+    seed_render_pipeline: wgpu::RenderPipeline,
+    sdf_render_pipeline: wgpu::RenderPipeline,
+}
+
+impl SignedDistanceField {
+    /// Uses R16Float instead of a format the adapter can't render into, see
+    /// [`SignedDistanceField::format`]. Fails if it can't render into either.
+    pub fn new(engine: &Engine, format: SdfFormat) -> anyhow::Result<SignedDistanceField> {
+        let format = match format.is_supported(engine.adapter()) {
+            true => format,
+            false => SdfFormat::R16Float,
+        };
+        if !format.is_supported(engine.adapter()) {
+            bail!("the adapter can't render signed distances into {format:?}");
+        }
+        let device = engine.device();
+
+        let side_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Side Buffer"),
+            contents: bytemuck::cast_slice(&[0u32]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // The seed pass has no ping-pong texture to read, but shares the
+        // group numbers of mask.wgsl with the combine pass
+        let empty_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[],
+                label: Some("empty_bind_group_layout"),
+            });
+
+        let empty_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &empty_bind_group_layout,
+            entries: &[],
+            label: Some("empty_bind_group"),
+        });
+
+        // The mask and the side it's currently measured from
+        let mask_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Uint,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("mask_bind_group_layout"),
+            });
+
+        // R32Float can't be filtered without an optional feature
+        let sdf_bind_group_layout = create_texture_bind_group_layout(
+            device,
+            wgpu::TextureSampleType::Float { filterable: false },
+            "sdf_bind_group_layout",
+        );
+
+        let textures = SdfTextures::new(
+            device,
+            &mask_bind_group_layout,
+            &sdf_bind_group_layout,
+            &side_buffer,
+            format,
+            engine.width(),
+            engine.height(),
+        );
+
+        let seed_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("SDF Seed Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &empty_bind_group_layout,
+                    &mask_bind_group_layout,
+                    engine.dimensions_bind_group_layout(),
+                    engine.options_bind_group_layout(),
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let seed_render_pipeline = create_render_pipeline(
+            "SDF Seed Render Pipeline",
+            device,
+            &seed_render_pipeline_layout,
+            engine::JFA_TEXTURE_FORMAT,
+            &[Vertex::desc()],
+            shader!("sdf_seed.wgsl", "mask.wgsl", "options.wgsl", "no_seed.wgsl"),
+        );
+
+        let sdf_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("SDF Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    engine.jfa_bind_group_layout(),
+                    &mask_bind_group_layout,
+                    engine.dimensions_bind_group_layout(),
+                    engine.options_bind_group_layout(),
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let sdf_render_pipeline = create_render_pipeline(
+            "SDF Render Pipeline",
+            device,
+            &sdf_render_pipeline_layout,
+            format.texture_format(),
+            &[Vertex::desc()],
+            shader!("sdf.wgsl", "mask.wgsl", "options.wgsl", "no_seed.wgsl"),
+        );
+
+        Ok(Self {
+            format,
+            side_buffer,
+            empty_bind_group,
+            mask_bind_group_layout,
+            sdf_bind_group_layout,
+            textures,
+            seed_render_pipeline,
+            sdf_render_pipeline,
+        })
+    }
+
+    /// The format of [`SignedDistanceField::texture`], which may not be the
+    /// requested one.
+    pub fn format(&self) -> SdfFormat {
+        self.format
+    }
+
+    /// The signed distances of the last [`SignedDistanceField::generate`].
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.textures.sdf_texture
+    }
+
+    /// Layout of [`SignedDistanceField::bind_group`], a non-filterable
+    /// `texture_2d<f32>`.
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.sdf_bind_group_layout
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.textures.sdf_bind_group
+    }
+
+    /// Computes the signed distance field of a row-major mask the size of
+    /// the engine, `true` inside the shape. Unless the engine wraps, pixels
+    /// beyond the border count as outside.
+    ///
+    /// Uses the engine's config for both jump floods, and fails without
+    /// drawing if it isn't [`crate::engine::EngineConfig::validate`].
+    pub fn generate(&mut self, engine: &mut Engine, mask: &[bool]) -> anyhow::Result<()> {
+        let (width, height) = (engine.width(), engine.height());
+        assert_eq!(
+            mask.len(),
+            (width * height) as usize,
+            "the mask doesn't match the engine size"
+        );
+
+        if (width, height) != (self.textures.width, self.textures.height) {
+            self.textures = SdfTextures::new(
+                engine.device(),
+                &self.mask_bind_group_layout,
+                &self.sdf_bind_group_layout,
+                &self.side_buffer,
+                self.format,
+                width,
+                height,
+            );
+        }
+
+        engine.config().validate()?;
+        engine.write_options();
+
+        let mask: Vec<u8> = mask.iter().map(|&inside| inside as u8).collect();
+        engine.queue().write_texture(
+            self.textures.mask_texture.as_image_copy(),
+            &mask,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(width),
+                rows_per_image: Some(height),
+            },
+            self.textures.mask_texture.size(),
+        );
+
+        // Outside first, so the first combine pass can clear the texture
+        for (side, load) in [
+            (0u32, wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT)),
+            (1u32, wgpu::LoadOp::Load),
+        ] {
+            engine
+                .queue()
+                .write_buffer(&self.side_buffer, 0, bytemuck::cast_slice(&[side]));
+
+            let mut encoder = engine.new_encoder();
+            {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("SDF Seed Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: engine.seed_texture_view(),
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(NO_SEED_COLOR),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                });
+
+                render_pass.set_pipeline(&self.seed_render_pipeline);
+                render_pass.set_bind_group(0, &self.empty_bind_group, &[]);
+                render_pass.set_bind_group(1, &self.textures.mask_bind_group, &[]);
+                render_pass.set_bind_group(2, engine.dimensions_bind_group(), &[]);
+                render_pass.set_bind_group(3, engine.options_bind_group(), &[]);
+                render_pass.set_vertex_buffer(0, engine.vertex_buffer().slice(..));
+                render_pass.draw(0..3, 0..1);
+            }
+            engine.queue().submit(std::iter::once(encoder.finish()));
+
+            engine.jump_flood();
+
+            let mut encoder = engine.new_encoder();
+            {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("SDF Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &self.textures.sdf_texture_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                });
+
+                render_pass.set_pipeline(&self.sdf_render_pipeline);
+                render_pass.set_bind_group(0, engine.output_bind_group(), &[]);
+                render_pass.set_bind_group(1, &self.textures.mask_bind_group, &[]);
+                render_pass.set_bind_group(2, engine.dimensions_bind_group(), &[]);
+                render_pass.set_bind_group(3, engine.options_bind_group(), &[]);
+                render_pass.set_vertex_buffer(0, engine.vertex_buffer().slice(..));
+                render_pass.draw(0..3, 0..1);
+            }
+            engine.queue().submit(std::iter::once(encoder.finish()));
+        }
+        Ok(())
+    }
+}
+
+/// The mask and distance textures, recreated when the engine size changes.
+struct SdfTextures {
+    width: u32,
+    height: u32,
+    mask_texture: wgpu::Texture,
+    sdf_texture: wgpu::Texture,
+    sdf_texture_view: wgpu::TextureView,
+    mask_bind_group: BindGroup,
+    sdf_bind_group: BindGroup,
+}
+
+impl SdfTextures {
+    fn new(
+        device: &Device,
+        mask_layout: &BindGroupLayout,
+        sdf_layout: &BindGroupLayout,
+        side_buffer: &Buffer,
+        format: SdfFormat,
+        width: u32,
+        height: u32,
+    ) -> SdfTextures {
+        let texture_desc = wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Uint,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+            label: Some("Mask Texture"),
+        };
+
+        let mask_texture = device.create_texture(&texture_desc);
+        let sdf_texture = device.create_texture(&wgpu::TextureDescriptor {
+            format: format.texture_format(),
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC,
+            label: Some("SDF Texture"),
+            ..texture_desc
+        });
+
+        let mask_texture_view = mask_texture.create_view(&Default::default());
+        let sdf_texture_view = sdf_texture.create_view(&Default::default());
+
+        let mask_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: mask_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&mask_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: side_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("mask_bind_group"),
+        });
+        let sdf_bind_group =
+            create_texture_bind_group(device, sdf_layout, &sdf_texture_view, "sdf_bind_group");
+
+        Self {
+            width,
+            height,
+            mask_texture,
+            sdf_texture,
+            sdf_texture_view,
+            mask_bind_group,
+            sdf_bind_group,
+        }
+    }
+}
+
+fn create_texture_bind_group_layout(
+    device: &Device,
+    sample_type: wgpu::TextureSampleType,
+    label: &str,
+) -> BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type,
+            },
+            count: None,
+        }],
+        label: Some(label),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pollster::FutureExt;
+
+    #[test]
+    fn disc_distances() {
+        let (width, height) = (32, 24);
+        let mut engine = Engine::headless(width, height, true).block_on().unwrap();
+        let (center, radius) = ([16., 12.], 6.);
+        let exact = |x: u32, y: u32| {
+            (x as f32 + 0.5 - center[0]).hypot(y as f32 + 0.5 - center[1]) - radius
+        };
+        let mask: Vec<bool> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| exact(x, y) < 0.)
+            .collect();
+
+        for format in [SdfFormat::R32Float, SdfFormat::R16Float] {
+            let mut sdf = SignedDistanceField::new(&engine, format).unwrap();
+            sdf.generate(&mut engine, &mask).unwrap();
+            let distances = sdf.read(&engine).block_on().unwrap();
+
+            for (i, distance) in distances.into_iter().enumerate() {
+                let (x, y) = (i as u32 % width, i as u32 / width);
+                assert_eq!(distance < 0., mask[i], "wrong side at ({x}, {y})");
+                // Measured to the edge between pixels, not the circle
+                assert!(
+                    (distance - exact(x, y)).abs() < 1.,
+                    "{distance} at ({x}, {y}), {} on the circle",
+                    exact(x, y)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+struct VertexInput {
+    @location(0) pos: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) pos: vec2<f32>,
+}
+
+@vertex
+fn vs_main(
+    point: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(point.pos, 0.0, 1.0);
+
+    out.pos = point.pos;
+    return out;
+}
+
+@group(0) @binding(0)
+var t_jfa: texture_2d<u32>;
+
+// Writes the signed distance of the pixels on `side`, the others are kept from
+// the run for the other side
+@fragment
+fn fs_main(@builtin(position) coords: vec4<f32>) -> @location(0) vec4<f32> {
+    let pos = vec2<i32>(coords.xy);
+    if mask(pos) != side {
+        discard;
+    }
+
+    // Without any pixel on the other side, the edge is at least the diagonal away
+    var nearest = length(dimensions);
+    let closest = textureLoad(t_jfa, pos, 0);
+    if !all(closest.xy == NO_SEED) {
+        nearest = pixel_distance(vec2<f32>(pos), vec2<f32>(closest.xy));
+    }
+    if side == 1u && options.wrap == 0u {
+        // The outside continues beyond the border
+        let border = min(pos + 1, vec2<i32>(dimensions) - pos);
+        nearest = min(nearest, f32(min(border.x, border.y)));
+    }
+
+    // The edge lies halfway between the pixel centres on either side of it
+    let sign = select(1., -1., side == 1u);
+    return vec4<f32>(sign * (nearest - 0.5), 0., 0., 1.);
+}
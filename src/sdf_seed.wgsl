@@ -0,0 +1,35 @@
+struct VertexInput {
+    @location(0) pos: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) pos: vec2<f32>,
+}
+
+@vertex
+fn vs_main(
+    point: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(point.pos, 0.0, 1.0);
+
+    out.pos = point.pos;
+    return out;
+}
+
+// Seeds the boundary of the other side: its pixels with a 4-neighbour on `side`
+@fragment
+fn fs_main(@builtin(position) coords: vec4<f32>) -> @location(0) vec4<u32> {
+    let pos = vec2<i32>(coords.xy);
+    let boundary = mask(pos) != side && (
+        mask(pos + vec2<i32>(-1, 0)) == side ||
+        mask(pos + vec2<i32>(1, 0)) == side ||
+        mask(pos + vec2<i32>(0, -1)) == side ||
+        mask(pos + vec2<i32>(0, 1)) == side
+    );
+    if !boundary {
+        return vec4<u32>(NO_SEED, 0u, 0u);
+    }
+    return vec4<u32>(vec2<u32>(pos), 0u, bitcast<u32>(1.));
+}
@@ -21,38 +21,23 @@ fn vs_main(
     return out;
 }
 
-struct MouseUniform {
-    pos: vec2<f32>,
-};
-
-@group(0) @binding(0)
-var<uniform> mouse: MouseUniform;
-
 @fragment
-fn fs_main(@location(0) in: vec2<f32>, @builtin(position) coords: vec4<f32>) -> @location(0) vec4<f32> {
-    let pos = vec2<f32>((in.x + 1) / 2, (in.y - 1) / -2);
-    let a = vec2<f32>(.1, .1);
-    let b = vec2<f32>(.2, .5);
-    let c = vec2<f32>(.9, .7);
-    let dist = 0.02;
-    if distance(coords.xy, mouse.pos) < 40 {
-        return seed(mouse.pos);
-    } else if distance(pos, a) < dist {
-        return seed(a*2048);
-    } else if distance(pos, b) < dist {
-        return seed(b*2048);
-    } else if distance(pos, c) < dist {
-        return seed(c*2048);
-        // return seed(coords.xy);
-    } else {
-        return vec4<f32>(1., 1., 1., 1.);
+fn fs_main(@builtin(position) coords: vec4<f32>) -> @location(0) vec4<u32> {
+    // Primitives are drawn over the discs, and every pixel they cover is a seed
+    let primitive = covering_primitive(coords.xy);
+    if primitive >= 0 {
+        let seed = primitives.primitives[primitive];
+        return vec4<u32>(vec2<u32>(coords.xy), seed.id, bitcast<u32>(seed.weight));
     }
-}
 
-fn seed(pos: vec2<f32>) -> vec4<f32> {
-    return vec4<f32>(encode_data(pos.x), encode_data(pos.y));
+    let index = covering_seed(coords.xy);
+    if index < 0 {
+        return vec4<u32>(NO_SEED, 0u, 0u);
+    }
+    return seed(seeds.seeds[index]);
 }
 
-fn encode_data(data: f32) -> vec2<f32> {
-    return vec2<f32>(floor(data / 255.)/255, (data % 255.)/255);
+// Stores the pixel the seed lies in, its id and the bits of its weight
+fn seed(seed: Seed) -> vec4<u32> {
+    return vec4<u32>(vec2<u32>(seed_pixel(seed)), seed.id, bitcast<u32>(seed.weight));
 }
@@ -0,0 +1,351 @@
+use crate::primitives::{GpuPrimitive, Primitive};
+use anyhow::bail;
+use wgpu::{BindGroup, BindGroupLayout, Buffer, Device, Queue};
+
+/// A disc of pixels that all start out with the seed's center as their
+/// nearest seed, always including the pixel the center lies in. Later seeds
+/// are drawn over earlier ones.
+///
+/// Matches `struct Seed` in `seeds.wgsl`.
+#[repr(C)]
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    bytemuck::Pod,
+    bytemuck::Zeroable,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct Seed {
+    /// Center in pixels.
+    pub position: [f32; 2],
+    /// Radius in pixels.
+    pub radius: f32,
+    pub id: u32,
+    /// Drawn into the material texture.
+    pub color: [f32; 4],
+    /// Used by [`crate::engine::EngineConfig::weighting`], 1 by default.
+    /// Must be positive, multiplicative weighting divides by it.
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    #[serde(skip)]
+    _padding: [f32; 3],
+}
+
+impl Seed {
+    pub fn new(id: u32, position: [f32; 2], radius: f32, color: [f32; 4]) -> Seed {
+        Seed {
+            position,
+            radius,
+            id,
+            color,
+            weight: 1.,
+            _padding: [0.; 3],
+        }
+    }
+
+    /// # Panics
+    ///
+    /// If the weight isn't a positive finite number.
+    pub fn with_weight(self, weight: f32) -> Seed {
+        assert!(
+            is_valid_weight(weight),
+            "weights must be positive, not {weight}"
+        );
+        Seed { weight, ..self }
+    }
+}
+
+pub(crate) fn default_weight() -> f32 {
+    1.
+}
+
+/// Whether multiplicative weighting can divide by a weight.
+pub(crate) fn is_valid_weight(weight: f32) -> bool {
+    weight.is_finite() && weight > 0.
+}
+
+/// Fails for a weight that isn't a positive finite number.
+pub(crate) fn check_weight(kind: &str, id: u32, weight: f32) -> anyhow::Result<()> {
+    if !is_valid_weight(weight) {
+        bail!("the weight of {kind} {id} isn't positive");
+    }
+    Ok(())
+}
+
+// The element count, padded to the alignment of the arrays
+const HEADER_SIZE: wgpu::BufferAddress = 16;
+
+/// The seed and primitive lists and their copies in storage buffers,
+/// uploaded lazily.
+pub(crate) struct SeedBuffer {
+    seeds: Vec<Seed>,
+    primitives: Vec<Primitive>,
+    seed_buffer: Buffer,
+    primitive_buffer: Buffer,
+    point_buffer: Buffer,
+    bind_group: BindGroup,
+    dirty: bool,
+}
+
+impl SeedBuffer {
+    pub(crate) fn new(device: &Device, layout: &BindGroupLayout) -> SeedBuffer {
+        let seed_buffer = create_buffer(device, "Seeds Buffer", HEADER_SIZE);
+        let primitive_buffer = create_buffer(device, "Primitives Buffer", HEADER_SIZE);
+        let point_buffer = create_buffer(device, "Points Buffer", 0);
+        let bind_group = create_bind_group(
+            device,
+            layout,
+            [&seed_buffer, &primitive_buffer, &point_buffer],
+        );
+
+        Self {
+            seeds: Vec::new(),
+            primitives: Vec::new(),
+            seed_buffer,
+            primitive_buffer,
+            point_buffer,
+            bind_group,
+            dirty: true,
+        }
+    }
+
+    /// The seeds, the primitives and their outline points.
+    pub(crate) fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
+        let entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[entry(0), entry(1), entry(2)],
+            label: Some("seeds_bind_group_layout"),
+        })
+    }
+
+    pub(crate) fn seeds(&self) -> &[Seed] {
+        &self.seeds
+    }
+
+    /// Fails without adding the seed if its weight isn't valid or its id is
+    /// already used.
+    pub(crate) fn add(&mut self, seed: Seed) -> anyhow::Result<()> {
+        check_weight("seed", seed.id, seed.weight)?;
+        self.check_unused(seed.id)?;
+        self.seeds.push(seed);
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub(crate) fn remove(&mut self, id: u32) -> Option<Seed> {
+        let index = self.seeds.iter().position(|seed| seed.id == id)?;
+        self.dirty = true;
+        Some(self.seeds.remove(index))
+    }
+
+    pub(crate) fn update(&mut self, seed: Seed) -> anyhow::Result<()> {
+        check_weight("seed", seed.id, seed.weight)?;
+        let Some(other) = self.seeds.iter_mut().find(|other| other.id == seed.id) else {
+            bail!("there is no seed with the id {}", seed.id);
+        };
+        *other = seed;
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.seeds.clear();
+        self.primitives.clear();
+        self.dirty = true;
+    }
+
+    pub(crate) fn primitives(&self) -> &[Primitive] {
+        &self.primitives
+    }
+
+    /// Fails without adding the primitive if its weight isn't valid or its
+    /// id is already used.
+    pub(crate) fn add_primitive(&mut self, primitive: Primitive) -> anyhow::Result<()> {
+        check_weight("primitive", primitive.id, primitive.weight)?;
+        self.check_unused(primitive.id)?;
+        self.primitives.push(primitive);
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Seeds and primitives share the label channel, so an id can only be
+    /// used once across both.
+    fn check_unused(&self, id: u32) -> anyhow::Result<()> {
+        let seed_ids = self.seeds.iter().map(|seed| seed.id);
+        let primitive_ids = self.primitives.iter().map(|primitive| primitive.id);
+        if seed_ids.chain(primitive_ids).any(|other| other == id) {
+            bail!("the id {id} is already used by a seed or primitive");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn remove_primitive(&mut self, id: u32) -> Option<Primitive> {
+        let index = self
+            .primitives
+            .iter()
+            .position(|primitive| primitive.id == id)?;
+        self.dirty = true;
+        Some(self.primitives.remove(index))
+    }
+
+    pub(crate) fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    /// Writes the seeds and primitives to the GPU if they changed, growing
+    /// the buffers if needed.
+    pub(crate) fn upload(&mut self, device: &Device, queue: &Queue, layout: &BindGroupLayout) {
+        if !self.dirty {
+            return;
+        }
+
+        let mut points = Vec::new();
+        let primitives: Vec<GpuPrimitive> = self
+            .primitives
+            .iter()
+            .map(|primitive| GpuPrimitive::new(primitive, &mut points))
+            .collect();
+
+        let seeds_size =
+            HEADER_SIZE + std::mem::size_of_val(&self.seeds[..]) as wgpu::BufferAddress;
+        let primitives_size =
+            HEADER_SIZE + std::mem::size_of_val(&primitives[..]) as wgpu::BufferAddress;
+        let points_size = std::mem::size_of_val(&points[..]) as wgpu::BufferAddress;
+
+        let mut grown = false;
+        for (buffer, label, size) in [
+            (&mut self.seed_buffer, "Seeds Buffer", seeds_size),
+            (
+                &mut self.primitive_buffer,
+                "Primitives Buffer",
+                primitives_size,
+            ),
+            (&mut self.point_buffer, "Points Buffer", points_size),
+        ] {
+            if size > buffer.size() {
+                *buffer = create_buffer(device, label, size);
+                grown = true;
+            }
+        }
+        if grown {
+            self.bind_group = create_bind_group(
+                device,
+                layout,
+                [
+                    &self.seed_buffer,
+                    &self.primitive_buffer,
+                    &self.point_buffer,
+                ],
+            );
+        }
+
+        write_array(queue, &self.seed_buffer, &self.seeds);
+        write_array(queue, &self.primitive_buffer, &primitives);
+        if !points.is_empty() {
+            queue.write_buffer(&self.point_buffer, 0, bytemuck::cast_slice(&points));
+        }
+        self.dirty = false;
+    }
+}
+
+/// Writes the element count followed by the elements.
+fn write_array<T: bytemuck::Pod>(queue: &Queue, buffer: &Buffer, elements: &[T]) {
+    queue.write_buffer(
+        buffer,
+        0,
+        bytemuck::cast_slice(&[elements.len() as u32, 0, 0, 0]),
+    );
+    if !elements.is_empty() {
+        queue.write_buffer(buffer, HEADER_SIZE, bytemuck::cast_slice(elements));
+    }
+}
+
+/// Rounds up to a power of two so growing one element at a time doesn't
+/// reallocate every time.
+fn create_buffer(device: &Device, label: &str, size: wgpu::BufferAddress) -> Buffer {
+    // Storage buffers can't be empty, and arrays need room for one element
+    let size = size.max(HEADER_SIZE + 64).next_power_of_two();
+
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    buffers: [&Buffer; 3],
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffers[0].as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: buffers[1].as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: buffers[2].as_entire_binding(),
+            },
+        ],
+        label: Some("seeds_bind_group"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::readback::NearestSeed;
+
+    #[test]
+    fn point_seeds_cover_their_pixel() {
+        let (width, height) = (16, 10);
+        let mut engine = pollster::block_on(Engine::headless(width, height, true)).unwrap();
+        // Discs this small contain no pixel center
+        engine
+            .add_seed(Seed::new(1, [5., 3.], 0., [1.; 4]))
+            .unwrap();
+        engine
+            .add_seed(Seed::new(2, [12., 7.], 0.5, [1.; 4]))
+            .unwrap();
+        engine.run().unwrap();
+
+        let nearest = pollster::block_on(engine.read_nearest()).unwrap();
+        assert_eq!(
+            nearest[(3 * width + 5) as usize],
+            Some(NearestSeed {
+                position: [5, 3],
+                id: 1
+            })
+        );
+        assert_eq!(
+            nearest[(7 * width + 12) as usize],
+            Some(NearestSeed {
+                position: [12, 7],
+                id: 2
+            })
+        );
+        assert!(nearest.iter().all(Option::is_some));
+    }
+}
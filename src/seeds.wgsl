@@ -0,0 +1,117 @@
+// Shared by the seed (seed.wgsl) and material (color.wgsl) passes, which
+// bind `dimensions`
+
+// Matches `Seed` in seeds.rs
+struct Seed {
+    position: vec2<f32>,
+    radius: f32,
+    id: u32,
+    color: vec4<f32>,
+    weight: f32,
+}
+
+struct Seeds {
+    count: u32,
+    seeds: array<Seed>,
+}
+
+@group(0) @binding(0)
+var<storage, read> seeds: Seeds;
+
+// Matches `GpuPrimitive` in primitives.rs
+struct Primitive {
+    first_point: u32,
+    point_count: u32,
+    // 0 open stroke, 1 closed stroke, 2 fill
+    kind: u32,
+    id: u32,
+    color: vec4<f32>,
+    thickness: f32,
+    weight: f32,
+}
+
+struct Primitives {
+    count: u32,
+    primitives: array<Primitive>,
+}
+
+@group(0) @binding(1)
+var<storage, read> primitives: Primitives;
+
+// The outlines of all primitives
+@group(0) @binding(2)
+var<storage, read> points: array<vec2<f32>>;
+
+// Index of the last seed whose disc covers the pixel, or -1. The pixel a
+// seed lies in is always covered, so small discs don't vanish.
+fn covering_seed(pos: vec2<f32>) -> i32 {
+    var found = -1;
+    for (var i = 0u; i < seeds.count; i++) {
+        let seed = seeds.seeds[i];
+        if distance(pos, seed.position) < seed.radius || all(floor(pos) == seed_pixel(seed)) {
+            found = i32(i);
+        }
+    }
+    return found;
+}
+
+// The pixel a seed lies in, clamped to the texture
+fn seed_pixel(seed: Seed) -> vec2<f32> {
+    return floor(clamp(seed.position, vec2<f32>(0.), dimensions - 1.));
+}
+
+// Index of the last primitive covering the pixel, or -1
+fn covering_primitive(pos: vec2<f32>) -> i32 {
+    var found = -1;
+    for (var i = 0u; i < primitives.count; i++) {
+        let primitive = primitives.primitives[i];
+        if primitive.point_count == 0u {
+            continue;
+        }
+        var covered = false;
+        if primitive.kind == 2u {
+            covered = inside_outline(pos, primitive);
+        } else {
+            covered = outline_distance(pos, primitive) <= max(primitive.thickness, 1.) / 2.;
+        }
+        if covered {
+            found = i32(i);
+        }
+    }
+    return found;
+}
+
+fn outline_distance(pos: vec2<f32>, primitive: Primitive) -> f32 {
+    let first = primitive.first_point;
+    var nearest = distance(pos, points[first]);
+    for (var i = 1u; i < primitive.point_count; i++) {
+        nearest = min(nearest, segment_distance(pos, points[first + i - 1u], points[first + i]));
+    }
+    if primitive.kind == 1u && primitive.point_count > 2u {
+        let last = first + primitive.point_count - 1u;
+        nearest = min(nearest, segment_distance(pos, points[last], points[first]));
+    }
+    return nearest;
+}
+
+fn segment_distance(pos: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let ab = b - a;
+    let t = clamp(dot(pos - a, ab) / max(dot(ab, ab), 1e-6), 0., 1.);
+    return distance(pos, a + t * ab);
+}
+
+// Even-odd rule, the outline is closed by a segment from its last point back to its first
+fn inside_outline(pos: vec2<f32>, primitive: Primitive) -> bool {
+    let first = primitive.first_point;
+    var inside = false;
+    var j = primitive.point_count - 1u;
+    for (var i = 0u; i < primitive.point_count; i++) {
+        let a = points[first + i];
+        let b = points[first + j];
+        if (a.y > pos.y) != (b.y > pos.y) && pos.x < a.x + (pos.y - a.y) * (b.x - a.x) / (b.y - a.y) {
+            inside = !inside;
+        }
+        j = i;
+    }
+    return inside;
+}
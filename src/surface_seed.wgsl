@@ -0,0 +1,33 @@
+struct VertexInput {
+    @location(0) pos: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) pos: vec2<f32>,
+}
+
+@vertex
+fn vs_main(
+    point: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(point.pos, 0.0, 1.0);
+
+    out.pos = point.pos;
+    return out;
+}
+
+@group(0) @binding(0)
+var t_material: texture_2d<f32>;
+
+// Seeds every pixel of the scene: neither white nor fully transparent
+@fragment
+fn fs_main(@builtin(position) coords: vec4<f32>) -> @location(0) vec4<u32> {
+    let pos = vec2<u32>(coords.xy);
+    let color = textureLoad(t_material, pos, 0);
+    if color.a == 0. || all(color.rgb == vec3<f32>(1.)) {
+        return vec4<u32>(NO_SEED, 0u, 0u);
+    }
+    return vec4<u32>(pos, 0u, bitcast<u32>(1.));
+}